@@ -1,15 +1,33 @@
 use clap::{App, AppSettings, Arg, ArgMatches};
 use clubdarn;
+use clubdarn::Paginated;
+use clubdarn::cache::ResponseCache;
+use clubdarn::catalogue::Catalogue;
+use clubdarn::filter::{self, SongFilter, SortKey};
+use clubdarn::local::LocalIndex;
+use clubdarn::machine::MachineProfiles;
+use config::Config;
 use error::*;
+use flatten;
 use serde::Serialize;
 use serde_json;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::str::FromStr;
+use std::time::Duration;
 use subcommand;
+use table::{self, Tabular};
 
 pub fn root() -> App<'static, 'static> {
     let subcommands = vec![subcommand::song::app(),
                            subcommand::series::app(),
                            subcommand::artist::app(),
-                           subcommand::category::app()];
+                           subcommand::category::app(),
+                           subcommand::compare::app(),
+                           subcommand::snapshot::app(),
+                           subcommand::diff::app(),
+                           subcommand::ranking::app(),
+                           subcommand::interactive::app()];
 
     app_from_crate!()
         .about(crate_description!())
@@ -19,12 +37,43 @@ pub fn root() -> App<'static, 'static> {
         .subcommands(subcommands)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Table,
+    Csv,
+    Tsv,
+    Ndjson,
+}
+
+pub const FORMATS: [&'static str; 5] = ["json", "table", "csv", "tsv", "ndjson"];
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Format::Json),
+            "table" => Ok(Format::Table),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "ndjson" => Ok(Format::Ndjson),
+            other => Err(format!("unknown output format {}", other).into()),
+        }
+    }
+}
+
 pub struct Printer {
     pub compact: bool,
+    pub format: Format,
+    // Dotted paths (e.g., `artist.name`) of the fields to output for each item
+    pub fields: Option<Vec<String>>,
+    // Output each item as a line, e.g., `{id}\t{title}`
+    pub template: Option<String>,
 }
 
 impl Printer {
-    fn stringify<T: Serialize>(&self, t: &T) -> Result<String> {
+    fn stringify<T: Serialize + ?Sized>(&self, t: &T) -> Result<String> {
         if self.compact {
                 serde_json::to_string(t)
             } else {
@@ -33,9 +82,46 @@ impl Printer {
             .chain_err(|| "failed to serialize JSON")
     }
 
-    pub fn stdout<T: Serialize>(&self, t: &T) -> Result<()> {
-        let s = self.stringify(t);
-        Ok(println!("{}", s?))
+    // Output that can't be shown as a table falls back to JSON. The other formats,
+    // as well as `fields` and `template`, output each item in a listing (e.g., songs
+    // in a page of results) separately.
+    pub fn stdout<T: Serialize + Tabular + ?Sized>(&self, t: &T) -> Result<()> {
+        if self.format == Format::Json && self.fields.is_none() && self.template.is_none() {
+            return Ok(println!("{}", self.stringify(t)?));
+        }
+
+        let value = serde_json::to_value(t).chain_err(|| "failed to serialize JSON")?;
+
+        if let Some(ref template) = self.template {
+            return Ok(println!("{}", flatten::template(&value, template)));
+        }
+
+        let fields = self.fields.as_ref().map(|fields| fields.as_slice());
+
+        let s = match self.format {
+            Format::Json => {
+                let items = flatten::rows(&value, fields)
+                    .into_iter()
+                    .map(flatten::to_object)
+                    .collect::<Vec<_>>();
+                self.stringify(&items)?
+            }
+            Format::Table => {
+                let table = match fields {
+                    Some(_) => Some(table::render_rows(&flatten::rows(&value, fields))),
+                    None => table::render::<T>(&value),
+                };
+                match table {
+                    Some(table) => table,
+                    None => self.stringify(t)?,
+                }
+            }
+            Format::Csv => flatten::delimited(&flatten::rows(&value, fields), ','),
+            Format::Tsv => flatten::delimited(&flatten::rows(&value, fields), '\t'),
+            Format::Ndjson => flatten::ndjson(&value, fields)?,
+        };
+
+        Ok(println!("{}", s))
     }
 }
 
@@ -43,56 +129,436 @@ pub struct Context<'a> {
     pub client: clubdarn::Client<'a>,
     pub printer: Printer,
     pub page: u32,
+    // Fetch every page, starting from `page`
+    pub all_pages: bool,
+    // Fetch pages until there are at least this many items
+    pub limit: Option<usize>,
+    pub offline: Option<LocalIndex>,
 }
 
 impl<'a> Context<'a> {
-    pub fn from_matches(matches: &'a ArgMatches) -> Result<Self> {
+    // Options not given on the command line are taken from `config`
+    pub fn from_matches(matches: &'a ArgMatches, config: &'a Config) -> Result<Self> {
         // Take the ArgMatches from the deepest level of subcommands
         if let (_, Some(subcommand_matches)) = matches.subcommand() {
-            return Self::from_matches(subcommand_matches);
+            return Self::from_matches(subcommand_matches, config);
         }
 
-        let printer = Printer { compact: matches.is_present("compact-output") };
+        let printer = Printer {
+            compact: config.is_present(matches, "compact-output"),
+            format: config.value_of(matches, "format").unwrap().parse::<Format>()?,
+            fields: config.value_of(matches, "fields")
+                .map(|fields| fields.split(',').map(|f| f.trim().to_string()).collect()),
+            template: config.value_of(matches, "template").map(|t| t.to_string()),
+        };
         let page = value_t!(matches, "page", u32)?;
+        let limit = if matches.is_present("limit") {
+            Some(value_t!(matches, "limit", usize)?)
+        } else {
+            None
+        };
+
+        let mut recommend = clubdarn::RecommendConfig::default();
+        if let Some(id) = config.value_of(matches, "recommend-comp-id") {
+            recommend.comp_id = id.parse().chain_err(|| format!("invalid compId {}", id))?;
+        }
+        if let Some(id) = config.value_of(matches, "recommend-contract-id") {
+            recommend.contract_id = id.parse()
+                .chain_err(|| format!("invalid contractId {}", id))?;
+        }
+        if let Some(key) = config.value_of(matches, "recommend-auth-key") {
+            recommend.comp_auth_key = key.to_string();
+        }
+        if let Some(format) = config.value_of(matches, "recommend-format") {
+            recommend.format = format.to_string();
+        }
+
+        // Profiles from the `--machines` file take precedence over the config file
+        let mut machines = config.machines.clone();
+        if let Some(path) = config.value_of(matches, "machines") {
+            let profiles = MachineProfiles::from_toml(&read_file(path)?)
+                .chain_err(|| format!("failed to load machine profiles from {}", path))?;
+
+            for (name, machine) in profiles.iter() {
+                machines.insert(name.clone(), machine.clone());
+            }
+        }
+
+        let machine = match config.value_of(matches, "serial-no") {
+            Some(name_or_serial_no) => Some(machines.resolve(name_or_serial_no)?),
+            None => None,
+        };
 
         let metadata = clubdarn::Metadata {
-            app_ver: matches.value_of("app-ver").unwrap(),
-            device_id: matches.value_of("device-id").unwrap(),
-            device_nm: matches.value_of("device-nm").unwrap(),
-            os_ver: matches.value_of("os-ver").unwrap(),
-            serial_no: matches.value_of("serial-no"),
+            app_ver: config.value_of(matches, "app-ver").unwrap(),
+            device_id: config.value_of(matches, "device-id").unwrap(),
+            device_nm: config.value_of(matches, "device-nm").unwrap(),
+            os_ver: config.value_of(matches, "os-ver").unwrap(),
+            serial_no: None,
         };
 
-        let client = clubdarn::Client::new(metadata).chain_err(|| "unable to create client")?;
+        let mut client = clubdarn::Client::new(metadata)
+            .chain_err(|| "unable to create client")?
+            .set_recommend_config(recommend)
+            .set_machine_profiles(machines);
+        if let Some(machine) = machine {
+            client = client.set_default_machine(machine);
+        }
+        if let Some(dir) = config.value_of(matches, "cache-dir") {
+            let ttl = config.value_of(matches, "cache-ttl").unwrap();
+            let ttl = ttl.parse::<u64>().chain_err(|| format!("invalid cache TTL {}", ttl))?;
+            client = client.set_cache(ResponseCache::new(dir, Duration::from_secs(ttl)));
+        }
+
+        let offline = match config.value_of(matches, "offline") {
+            Some(path) => {
+                let file = File::open(path).chain_err(|| format!("failed to open {}", path))?;
+                let index = LocalIndex::read(BufReader::new(file))
+                    .chain_err(|| format!("failed to read snapshot from {}", path))?;
+                Some(index)
+            }
+            None => None,
+        };
 
         Ok(Context {
             client: client,
             printer: printer,
             page: page,
+            all_pages: matches.is_present("all-pages"),
+            limit: limit,
+            offline: offline,
+        })
+    }
+
+    fn is_multi_page(&self) -> bool {
+        self.all_pages || self.limit.is_some()
+    }
+
+    fn report_progress<T>(&self, page: &Paginated<T>) -> Result<()> {
+        writeln!(&mut io::stderr(),
+                 "Fetched page {} of {}",
+                 page.page,
+                 page.total_pages)
+            .chain_err(|| "failed to write to stderr")
+    }
+
+    // Merges pages until there are at least `limit` items, if given
+    fn merge_pages<T, P>(&self, pages: P, limit: Option<usize>) -> Result<Paginated<T>>
+        where P: Iterator<Item = clubdarn::Result<Paginated<T>>>
+    {
+        let mut merged: Option<Paginated<T>> = None;
+
+        for page in pages {
+            let page = page?;
+            self.report_progress(&page)?;
+
+            let result = match merged.take() {
+                Some(mut result) => {
+                    result.items.extend(page.items);
+                    result
+                }
+                None => page,
+            };
+
+            let is_done = limit.map_or(false, |limit| result.items.len() >= limit);
+            merged = Some(result);
+
+            if is_done {
+                break;
+            }
+        }
+
+        let mut result = match merged {
+            Some(result) => result,
+            None => Err("no results")?,
+        };
+
+        if let Some(limit) = limit {
+            result.items.truncate(limit);
+        }
+        result.page = 1;
+        result.total_pages = 1;
+        result.total_items = result.items.len() as u32;
+
+        Ok(result)
+    }
+
+    // Only the first page is fetched, unless `--all-pages` or `--limit` is given
+    pub fn fetch<T, P>(&self, mut pages: P) -> Result<Paginated<T>>
+        where P: Iterator<Item = clubdarn::Result<Paginated<T>>>
+    {
+        if self.is_multi_page() {
+            self.merge_pages(pages, self.limit)
+        } else {
+            match pages.next() {
+                Some(page) => Ok(page?),
+                None => Err("no results".into()),
+            }
+        }
+    }
+
+    // Like `fetch`, but NDJSON output is printed as each page is fetched
+    pub fn print_pages<T, P>(&self, pages: P) -> Result<()>
+        where T: Serialize + Tabular,
+              P: Iterator<Item = clubdarn::Result<Paginated<T>>>
+    {
+        if !self.is_multi_page() || self.printer.format != Format::Ndjson {
+            return self.printer.stdout(&self.fetch(pages)?);
+        }
+
+        let mut remaining = self.limit;
+
+        for page in pages {
+            let mut page = page?;
+            self.report_progress(&page)?;
+
+            if let Some(remaining_items) = remaining {
+                page.items.truncate(remaining_items);
+                remaining = Some(remaining_items - page.items.len());
+            }
+
+            if !page.items.is_empty() {
+                self.printer.stdout(&page)?;
+            }
+
+            if remaining == Some(0) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    // The local snapshot in offline mode, otherwise the ClubDAM API
+    pub fn catalogue(&self) -> &Catalogue {
+        match self.offline {
+            Some(ref index) => index,
+            None => &self.client,
+        }
+    }
+
+    // For subcommands that can only be answered by the ClubDAM API
+    pub fn require_online(&self, subcommand: &str) -> Result<()> {
+        if self.offline.is_some() {
+            Err(format!("`{}` isn't available with --offline", subcommand))?
+        }
+
+        Ok(())
+    }
+}
+
+pub fn read_file(path: &str) -> Result<String> {
+    let mut contents = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .chain_err(|| format!("failed to read {}", path))?;
+
+    Ok(contents)
+}
+
+pub struct SongFilterArgs {
+    pub filter: SongFilter,
+    pub sort: Option<SortKey>,
+    pub descending: bool,
+}
+
+impl SongFilterArgs {
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        if let (_, Some(subcommand_matches)) = matches.subcommand() {
+            return Self::from_matches(subcommand_matches);
+        }
+
+        let mut song_filter = SongFilter::new();
+
+        if matches.is_present("has-video") {
+            song_filter = song_filter.has_video(true);
+        }
+        if matches.is_present("has-series") {
+            song_filter = song_filter.has_series(true);
+        }
+        if let Some(date) = matches.value_of("added-after") {
+            song_filter = song_filter.added_after(date)?;
+        }
+        if let Some(date) = matches.value_of("added-before") {
+            song_filter = song_filter.added_before(date)?;
+        }
+        if let Some(kana) = matches.value_of("first-kana") {
+            song_filter = song_filter.first_kana(kana.chars().next().unwrap());
+        }
+
+        let sort = match matches.value_of("sort") {
+            Some(key) => Some(key.parse::<SortKey>()?),
+            None => None,
+        };
+
+        Ok(SongFilterArgs {
+            filter: song_filter,
+            sort: sort,
+            descending: matches.is_present("reverse"),
         })
     }
+
+    pub fn is_active(&self) -> bool {
+        !self.filter.is_empty() || self.sort.is_some()
+    }
+
+    // Pages are fetched as usual (see `Context::fetch`) unless filtering or sorting is
+    // requested, in which case every page is fetched so that results are consistent.
+    // `--limit` then applies to the filtered results.
+    pub fn fetch<P>(&self, context: &Context, pages: P) -> Result<Paginated<clubdarn::Song>>
+        where P: Iterator<Item = clubdarn::Result<Paginated<clubdarn::Song>>>
+    {
+        if !self.is_active() {
+            return context.fetch(pages);
+        }
+
+        let mut result = context.merge_pages(pages, None)?;
+        self.apply(&mut result);
+
+        if let Some(limit) = context.limit {
+            result.items.truncate(limit);
+            result.total_items = result.items.len() as u32;
+        }
+
+        Ok(result)
+    }
+
+    pub fn print<P>(&self, context: &Context, pages: P) -> Result<()>
+        where P: Iterator<Item = clubdarn::Result<Paginated<clubdarn::Song>>>
+    {
+        if self.is_active() {
+            context.printer.stdout(&self.fetch(context, pages)?)
+        } else {
+            context.print_pages(pages)
+        }
+    }
+
+    pub fn apply(&self, page: &mut Paginated<clubdarn::Song>) {
+        self.filter.apply(page);
+
+        if let Some(key) = self.sort {
+            filter::sort_songs(&mut page.items, key, self.descending);
+        }
+    }
+
+    // Like `apply`, for recommend API results that keep their own fields
+    pub fn apply_recommended(&self, page: &mut Paginated<clubdarn::RecommendedSong>) {
+        page.items.retain(|item| self.filter.matches(&item.song));
+        page.total_items = page.items.len() as u32;
+
+        if let Some(key) = self.sort {
+            filter::sort_songs_by(&mut page.items, key, self.descending, |item| &item.song);
+        }
+    }
+
+    // Recommend API results are missing fields that filters and some sort keys use
+    pub fn needs_enrichment(&self) -> bool {
+        !self.filter.is_empty() || self.sort.map_or(false, |key| key.needs_details())
+    }
 }
 
 pub trait AppExt {
     fn with_global_args(self) -> Self;
+    fn with_song_filter_args(self) -> Self;
 }
 
 impl AppExt for App<'static, 'static> {
     fn with_global_args(self) -> Self {
         let default_metadata = clubdarn::Metadata::default();
 
-        self.arg(Arg::with_name("compact-output")
+        self.arg(Arg::with_name("config")
+                .help("TOML file of defaults for these options \
+                       [default: ~/.config/clubdarn/config.toml]")
+                .long("config")
+                .value_name("FILE")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("profile")
+                .help("Named profile in the config file to take defaults from")
+                .long("profile")
+                .value_name("PROFILE")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("compact-output")
                 .help("Compact JSON output without pretty-printing")
                 .long("compact-output")
                 .short("c")
                 .global(true))
+            .arg(Arg::with_name("no-compact-output")
+                .help("Pretty-print JSON output, even if the config file sets compact-output")
+                .long("no-compact-output")
+                .conflicts_with("compact-output")
+                .global(true))
+            .arg(Arg::with_name("format")
+                .help("Output format. Results that can't be shown as a table are shown as JSON.")
+                .long("format")
+                .short("f")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .default_value("json")
+                .possible_values(&FORMATS)
+                .global(true))
+            .arg(Arg::with_name("fields")
+                .help("Comma-separated fields to output for each item \
+                       (e.g., id,title,artist.name). Fields of wrapped songs can be given \
+                       with or without `song.`")
+                .long("fields")
+                .value_name("FIELDS")
+                .takes_value(true)
+                .empty_values(false)
+                .global(true))
+            .arg(Arg::with_name("template")
+                .help("Output each item using a template (e.g., '{id}\\t{title}'), \
+                       ignoring --format")
+                .long("template")
+                .value_name("TEMPLATE")
+                .takes_value(true)
+                .conflicts_with("fields")
+                .global(true))
             .arg(Arg::with_name("serial-no")
-                .help("Unique ID for karaoke machine (e.g., AB316238)")
+                .help("Unique ID for karaoke machine (e.g., AB316238), or a machine profile name")
                 .long("serial-no")
                 .short("s")
                 .value_name("SERIAL_NO")
                 .takes_value(true)
                 .global(true))
+            .arg(Arg::with_name("machines")
+                .help("TOML file of named machine profiles, in the same format as the `machines` \
+                       section of the config file")
+                .long("machines")
+                .value_name("FILE")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("offline")
+                .help("Answer queries from a snapshot file instead of the ClubDAM API")
+                .long("offline")
+                .value_name("SNAPSHOT")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("no-offline")
+                .help("Use the ClubDAM API, even if the config file sets a snapshot for --offline")
+                .long("no-offline")
+                .conflicts_with("offline")
+                .global(true))
+            .arg(Arg::with_name("cache-dir")
+                .help("Directory to cache API responses in, reusing them until they expire")
+                .long("cache-dir")
+                .value_name("DIR")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("no-cache-dir")
+                .help("Don't cache API responses, even if the config file sets a --cache-dir")
+                .long("no-cache-dir")
+                .conflicts_with("cache-dir")
+                .global(true))
+            .arg(Arg::with_name("cache-ttl")
+                .help("Seconds that cached responses are reused for")
+                .long("cache-ttl")
+                .value_name("SECONDS")
+                .default_value("3600")
+                .takes_value(true)
+                .global(true))
             .arg(Arg::with_name("page")
                 .help("Page number for pagination")
                 .long("page")
@@ -101,6 +567,16 @@ impl AppExt for App<'static, 'static> {
                 .takes_value(true)
                 .default_value("1")
                 .global(true))
+            .arg(Arg::with_name("all-pages")
+                .help("Fetch every page, starting from --page")
+                .long("all-pages")
+                .global(true))
+            .arg(Arg::with_name("limit")
+                .help("Fetch pages until there are this many items, starting from --page")
+                .long("limit")
+                .value_name("COUNT")
+                .takes_value(true)
+                .global(true))
             .arg(Arg::with_name("app-ver")
                 .help("appVer sent to ClubDAM API")
                 .long("app-ver")
@@ -129,5 +605,145 @@ impl AppExt for App<'static, 'static> {
                 .takes_value(true)
                 .default_value(default_metadata.os_ver)
                 .global(true))
+            .arg(Arg::with_name("recommend-comp-id")
+                .help("compId sent to the recommend API [default: 1]")
+                .long("recommend-comp-id")
+                .value_name("COMP_ID")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("recommend-contract-id")
+                .help("contractId sent to the recommend API [default: 1]")
+                .long("recommend-contract-id")
+                .value_name("CONTRACT_ID")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("recommend-auth-key")
+                .help("compAuthKey sent to the recommend API [default: same as Denmoku]")
+                .long("recommend-auth-key")
+                .value_name("AUTH_KEY")
+                .takes_value(true)
+                .global(true))
+            .arg(Arg::with_name("recommend-format")
+                .help("format sent to the recommend API [default: json]")
+                .long("recommend-format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .global(true))
+    }
+
+    fn with_song_filter_args(self) -> Self {
+        self.arg(Arg::with_name("has-video")
+                .help("Only include songs with a video")
+                .long("has-video"))
+            .arg(Arg::with_name("has-series")
+                .help("Only include songs that belong to a series")
+                .long("has-series"))
+            .arg(Arg::with_name("added-after")
+                .help("Only include songs added on or after this date (e.g., 2017/03/15)")
+                .long("added-after")
+                .value_name("DATE")
+                .takes_value(true))
+            .arg(Arg::with_name("added-before")
+                .help("Only include songs added on or before this date (e.g., 2017/03/15)")
+                .long("added-before")
+                .value_name("DATE")
+                .takes_value(true))
+            .arg(Arg::with_name("first-kana")
+                .help("Only include songs whose title starts with this kana")
+                .long("first-kana")
+                .value_name("KANA")
+                .empty_values(false)
+                .takes_value(true))
+            .arg(Arg::with_name("sort")
+                .help("Sort songs by the given key")
+                .long("sort")
+                .value_name("SORT_KEY")
+                .takes_value(true)
+                .possible_values(&filter::SORT_KEYS))
+            .arg(Arg::with_name("reverse")
+                .help("Sort in descending order")
+                .long("reverse")
+                .requires("sort"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(limit: Option<usize>) -> Context<'static> {
+        Context {
+            client: clubdarn::Client::new(clubdarn::Metadata::default()).unwrap(),
+            printer: Printer {
+                compact: true,
+                format: Format::Json,
+                fields: None,
+                template: None,
+            },
+            page: 1,
+            all_pages: limit.is_none(),
+            limit: limit,
+            offline: None,
+        }
+    }
+
+    // Three pages of three items each, numbered from 1
+    fn pages() -> Vec<clubdarn::Result<Paginated<u32>>> {
+        (0..3)
+            .map(|index| {
+                Ok(Paginated {
+                    page: index + 1,
+                    artist_category_id: "010000".into(),
+                    series_category_id: None,
+                    total_items: 9,
+                    total_pages: 3,
+                    items: (index * 3 + 1..index * 3 + 4).collect(),
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merges_every_page() {
+        let merged = context(None).merge_pages(pages().into_iter(), None).unwrap();
+
+        assert_eq!(merged.items, (1..10).collect::<Vec<_>>());
+        assert_eq!(merged.page, 1);
+        assert_eq!(merged.total_pages, 1);
+        assert_eq!(merged.total_items, 9);
+    }
+
+    #[test]
+    fn stops_once_the_limit_is_reached() {
+        let mut pages = pages();
+        // Never fetched, since the limit is reached on the second page
+        pages[2] = Err("unexpected page".into());
+
+        let merged = context(Some(5)).fetch(pages.into_iter()).unwrap();
+
+        assert_eq!(merged.items, vec![1, 2, 3, 4, 5]);
+        assert_eq!(merged.total_items, 5);
+    }
+
+    #[test]
+    fn first_page_only_by_default() {
+        let mut context = context(None);
+        context.all_pages = false;
+
+        let page = context.fetch(pages().into_iter()).unwrap();
+
+        assert_eq!(page.items, vec![1, 2, 3]);
+        assert_eq!(page.total_pages, 3);
+    }
+
+    #[test]
+    fn errors_and_no_pages() {
+        let mut pages = pages();
+        pages[1] = Err("failed".into());
+
+        assert!(context(None).merge_pages(pages.into_iter(), None).is_err());
+
+        let no_pages: Vec<clubdarn::Result<Paginated<u32>>> = Vec::new();
+        assert!(context(None).merge_pages(no_pages.into_iter(), None).is_err());
     }
 }
@@ -0,0 +1,278 @@
+// Defaults for the global options, read from a TOML file. Keys are the long
+// option names, and options given on the command line take precedence, e.g.:
+//
+//     serial-no = "home"
+//     format = "table"
+//     device-id = "..."
+//
+//     [cache]
+//     dir = "/home/me/.cache/clubdarn/responses"
+//     ttl = 3600
+//
+//     [machines.home]
+//     serial-no = "AB316238"
+//     model = "LIVE DAM STADIUM"
+//
+//     [profiles.work]
+//     serial-no = "CD123456"
+//     compact-output = true
+//
+//     [profiles.travel]
+//     offline = "/home/me/.cache/clubdarn/snapshot.jsonl"
+//
+// Settings in the `cache` section are the `--cache-` options without the prefix (e.g.,
+// `dir` for `--cache-dir`), and can also be set in profiles. Settings in a profile
+// (selected with `--profile`) override the top-level ones. Settings that are switched on
+// (e.g., `compact-output` or `cache.dir`) can be switched off for a single run with their
+// `--no-` option (e.g., `--no-cache-dir`).
+
+use app;
+use clap::ArgMatches;
+use clubdarn::machine::MachineProfiles;
+use error::*;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use toml;
+
+const SETTINGS: [&'static str; 17] = ["compact-output",
+                                      "offline",
+                                      "cache-dir",
+                                      "cache-ttl",
+                                      "format",
+                                      "fields",
+                                      "template",
+                                      "serial-no",
+                                      "machines",
+                                      "app-ver",
+                                      "device-id",
+                                      "device-nm",
+                                      "os-ver",
+                                      "recommend-comp-id",
+                                      "recommend-contract-id",
+                                      "recommend-auth-key",
+                                      "recommend-format"];
+
+#[derive(Debug, Default)]
+pub struct Config {
+    settings: BTreeMap<String, String>,
+    pub machines: MachineProfiles,
+}
+
+impl Config {
+    // Reads the file given by `--config`, or the default config file if it exists
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        // Take the ArgMatches from the deepest level of subcommands
+        if let (_, Some(subcommand_matches)) = matches.subcommand() {
+            return Self::from_matches(subcommand_matches);
+        }
+
+        let profile = matches.value_of("profile");
+
+        let path = match matches.value_of("config") {
+            Some(path) => path.to_string(),
+            None => {
+                match default_path() {
+                    Some(ref path) if path.exists() => path.to_string_lossy().into_owned(),
+                    _ if profile.is_some() => Err("--profile requires a config file")?,
+                    _ => return Ok(Config::default()),
+                }
+            }
+        };
+
+        Self::parse(&app::read_file(&path)?, profile)
+            .chain_err(|| format!("failed to load config from {}", path))
+    }
+
+    pub fn parse(input: &str, profile: Option<&str>) -> Result<Self> {
+        let mut parser = toml::Parser::new(input);
+        let mut table = match parser.parse() {
+            Some(table) => table,
+            None => {
+                let errors = parser.errors
+                    .iter()
+                    .map(|e| format!("line {}: {}", parser.to_linecol(e.lo).0 + 1, e.desc))
+                    .collect::<Vec<_>>();
+                Err(errors.join(", "))?
+            }
+        };
+
+        // `machines` may also be the path to a file of machine profiles
+        let machines = match table.remove("machines") {
+            Some(toml::Value::Table(machines)) => MachineProfiles::from_table(machines)?,
+            Some(path) => {
+                table.insert("machines".to_string(), path);
+                MachineProfiles::new()
+            }
+            None => MachineProfiles::new(),
+        };
+
+        let mut profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => Err("`profiles` should be a table")?,
+            None => toml::Table::new(),
+        };
+
+        let mut settings = parse_settings(table)?;
+
+        if let Some(name) = profile {
+            match profiles.remove(name) {
+                Some(toml::Value::Table(profile)) => {
+                    let overrides = parse_settings(profile)
+                        .chain_err(|| format!("invalid profile {}", name))?;
+                    settings.extend(overrides);
+                }
+                Some(_) => Err(format!("profile {} should be a table", name))?,
+                None => Err(format!("no profile named {}", name))?,
+            }
+        }
+
+        Ok(Config {
+            settings: settings,
+            machines: machines,
+        })
+    }
+
+    // A value given on the command line, then the config file, then the option's default.
+    // The config file is skipped if the option is switched off with `--no-<name>`.
+    pub fn value_of<'a>(&'a self, matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+        if matches.occurrences_of(name) > 0 || is_negated(matches, name) {
+            return matches.value_of(name);
+        }
+
+        self.settings.get(name).map(|value| value.as_str()).or_else(|| matches.value_of(name))
+    }
+
+    pub fn is_present(&self, matches: &ArgMatches, name: &str) -> bool {
+        if is_negated(matches, name) {
+            return false;
+        }
+
+        matches.is_present(name) || self.settings.get(name).map_or(false, |value| value == "true")
+    }
+}
+
+fn is_negated(matches: &ArgMatches, name: &str) -> bool {
+    matches.is_present(&format!("no-{}", name))
+}
+
+// `$XDG_CONFIG_HOME/clubdarn/config.toml`, or `~/.config/clubdarn/config.toml`
+fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("clubdarn").join("config.toml"))
+}
+
+// Settings in sections (e.g., `ttl` in `[cache]`) are named after the section
+// and the setting (e.g., `cache-ttl`)
+fn parse_settings(table: toml::Table) -> Result<BTreeMap<String, String>> {
+    let mut settings = BTreeMap::new();
+
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::Table(section) => {
+                let section = section.into_iter()
+                    .map(|(name, value)| (format!("{}-{}", key, name), value))
+                    .collect();
+                settings.extend(parse_settings(section)?);
+                continue;
+            }
+            toml::Value::String(s) => s,
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            _ => Err(format!("{} should be a string, integer or boolean", key))?,
+        };
+
+        if !SETTINGS.contains(&key.as_str()) {
+            Err(format!("unknown setting {}", key))?
+        }
+
+        settings.insert(key, value);
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &'static str = r#"
+serial-no = "home"
+format = "table"
+
+[machines.home]
+serial-no = "AB316238"
+model = "LIVE DAM STADIUM"
+
+[profiles.work]
+serial-no = "CD123456"
+compact-output = true
+"#;
+
+    fn setting<'c>(config: &'c Config, name: &str) -> Option<&'c str> {
+        config.settings.get(name).map(String::as_str)
+    }
+
+    #[test]
+    fn top_level_settings() {
+        let config = Config::parse(INPUT, None).unwrap();
+
+        assert_eq!(setting(&config, "serial-no"), Some("home"));
+        assert_eq!(setting(&config, "format"), Some("table"));
+        assert_eq!(setting(&config, "compact-output"), None);
+    }
+
+    #[test]
+    fn profile_overrides_top_level_settings() {
+        let config = Config::parse(INPUT, Some("work")).unwrap();
+
+        assert_eq!(setting(&config, "serial-no"), Some("CD123456"));
+        assert_eq!(setting(&config, "format"), Some("table"));
+        assert_eq!(setting(&config, "compact-output"), Some("true"));
+    }
+
+    #[test]
+    fn unknown_profile() {
+        assert!(Config::parse(INPUT, Some("home")).is_err());
+    }
+
+    #[test]
+    fn unknown_settings() {
+        assert!(Config::parse("colour = true", None).is_err());
+        assert!(Config::parse("[profiles.work]\ncolour = true", Some("work")).is_err());
+    }
+
+    #[test]
+    fn sections() {
+        let input = "[cache]\ndir = \"cache\"\n[profiles.fresh.cache]\nttl = 0";
+        let config = Config::parse(input, Some("fresh")).unwrap();
+
+        assert_eq!(setting(&config, "cache-dir"), Some("cache"));
+        assert_eq!(setting(&config, "cache-ttl"), Some("0"));
+        assert!(Config::parse("[cache]\nsize = 100", None).is_err());
+    }
+
+    #[test]
+    fn machines_table() {
+        let config = Config::parse(INPUT, None).unwrap();
+
+        assert_eq!(config.machines.get("home").map(|m| m.serial_no.as_str()),
+                   Some("AB316238"));
+        assert_eq!(setting(&config, "machines"), None);
+    }
+
+    #[test]
+    fn machines_path() {
+        let config = Config::parse("machines = \"machines.toml\"", None).unwrap();
+
+        assert!(config.machines.get("home").is_none());
+        assert_eq!(setting(&config, "machines"), Some("machines.toml"));
+    }
+
+    #[test]
+    fn invalid_machine() {
+        assert!(Config::parse("[machines.home]\nmodel = \"LIVE DAM STADIUM\"", None).is_err());
+    }
+}
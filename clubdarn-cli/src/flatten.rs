@@ -0,0 +1,243 @@
+use serde_json::{self, Map, Value};
+
+pub type Row = Vec<(String, Value)>;
+
+// The list of items in a result, e.g., the songs in a page of results.
+// Results that aren't lists are treated as a single item.
+pub fn items(value: &Value) -> Vec<&Value> {
+    if let Some(items) = value.as_array() {
+        return items.iter().collect();
+    }
+
+    // Pages of results, or results with a list of songs (e.g., an artist and their songs)
+    let list = value.get("items")
+        .or_else(|| value.get("songs").and_then(|songs| songs.get("items").or(Some(songs))));
+
+    match list.and_then(Value::as_array) {
+        Some(items) => items.iter().collect(),
+        None => vec![value],
+    }
+}
+
+// The song in an item. Some listings wrap songs with extra fields (e.g., discography,
+// recommendations), and other items are returned as-is.
+pub fn song(item: &Value) -> &Value {
+    item.get("song").unwrap_or(item)
+}
+
+// Looks up a dotted path (e.g., `artist.name`) in an item, then in its song,
+// so `id` finds `song.id` unless the wrapper has an `id` of its own
+pub fn lookup<'v>(item: &'v Value, path: &str) -> Option<&'v Value> {
+    fn find<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+        path.split('.').fold(Some(value), |value, key| value.and_then(|v| v.get(key)))
+    }
+
+    find(item, path).or_else(|| find(song(item), path))
+}
+
+// Flattens nested objects into dotted keys, e.g., `{"artist": {"name": ...}}` into
+// `artist.name`. Arrays are kept as-is.
+pub fn flatten(item: &Value) -> Row {
+    let mut row = Vec::new();
+    flatten_into(item, String::new(), &mut row);
+    row
+}
+
+fn flatten_into(value: &Value, key: String, row: &mut Row) {
+    match *value {
+        Value::Object(ref map) => {
+            for (name, value) in map.iter() {
+                let key = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", key, name)
+                };
+                flatten_into(value, key, row);
+            }
+        }
+        ref other => row.push((key, other.clone())),
+    }
+}
+
+// Only the given fields, in order. Missing fields are null.
+pub fn select(item: &Value, fields: &[String]) -> Row {
+    fields.iter()
+        .map(|field| (field.clone(), lookup(item, field).cloned().unwrap_or(Value::Null)))
+        .collect()
+}
+
+// Rows for each item, either with every field or only the given ones
+pub fn rows(value: &Value, fields: Option<&[String]>) -> Vec<Row> {
+    items(value)
+        .into_iter()
+        .map(|item| match fields {
+            Some(fields) => select(item, fields),
+            None => flatten(item),
+        })
+        .collect()
+}
+
+pub fn to_object(row: Row) -> Value {
+    let mut map = Map::new();
+    for (key, value) in row {
+        map.insert(key, value);
+    }
+    Value::Object(map)
+}
+
+// Strings without quotes, null as an empty string, and anything else as JSON
+pub fn text(value: &Value) -> String {
+    match *value {
+        Value::String(ref s) => s.clone(),
+        Value::Null => String::new(),
+        ref other => other.to_string(),
+    }
+}
+
+pub fn quote_csv(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// TSV has no quoting, so tabs and line breaks are replaced with spaces
+fn escape_tsv(field: &str) -> String {
+    field.replace(|c: char| c == '\t' || c == '\n' || c == '\r', " ")
+}
+
+fn escape(field: &str, delimiter: char) -> String {
+    if delimiter == ',' {
+        quote_csv(field)
+    } else {
+        escape_tsv(field)
+    }
+}
+
+// The union of every row's keys, in the order they're first seen
+pub fn headers(rows: &[Row]) -> Vec<&str> {
+    let mut headers: Vec<&str> = Vec::new();
+
+    for row in rows {
+        for &(ref key, _) in row {
+            if !headers.contains(&key.as_str()) {
+                headers.push(key);
+            }
+        }
+    }
+
+    headers
+}
+
+// Renders rows with a header row, leaving missing fields empty
+pub fn delimited(rows: &[Row], delimiter: char) -> String {
+    let headers = headers(rows);
+    let separator = delimiter.to_string();
+
+    let header_row = headers.iter().map(|h| escape(h, delimiter)).collect::<Vec<_>>();
+    let mut lines = vec![header_row.join(&separator)];
+
+    for row in rows {
+        let line = headers.iter()
+            .map(|header| {
+                row.iter()
+                    .find(|&&(ref key, _)| key == header)
+                    .map_or(String::new(), |&(_, ref value)| escape(&text(value), delimiter))
+            })
+            .collect::<Vec<_>>()
+            .join(&separator);
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+// One compact JSON item per line. If fields are given, each item only has those fields.
+pub fn ndjson(value: &Value, fields: Option<&[String]>) -> serde_json::Result<String> {
+    let lines = match fields {
+        Some(_) => {
+            rows(value, fields)
+                .into_iter()
+                .map(|row| serde_json::to_string(&to_object(row)))
+                .collect::<serde_json::Result<Vec<_>>>()?
+        }
+        None => {
+            items(value)
+                .into_iter()
+                .map(serde_json::to_string)
+                .collect::<serde_json::Result<Vec<_>>>()?
+        }
+    };
+
+    Ok(lines.join("\n"))
+}
+
+// Fills in `{path}` placeholders (e.g., `{id}\t{artist.name}`) once per item.
+// `\t`, `\n` and `\\` are unescaped, since shells usually pass them through as-is.
+pub fn template(value: &Value, template: &str) -> String {
+    let template = template.replace("\\\\", "\u{0}")
+        .replace("\\t", "\t")
+        .replace("\\n", "\n")
+        .replace("\u{0}", "\\");
+
+    items(value)
+        .into_iter()
+        .map(|item| {
+            let mut line = String::new();
+            let mut rest = template.as_str();
+
+            while let Some(start) = rest.find('{') {
+                let end = match rest[start..].find('}') {
+                    Some(end) => start + end,
+                    None => break,
+                };
+
+                line.push_str(&rest[..start]);
+                line.push_str(&lookup(item, &rest[start + 1..end]).map_or(String::new(), text));
+                rest = &rest[end + 1..];
+            }
+
+            line.push_str(rest);
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn quotes_csv_only_when_needed() {
+        assert_eq!(quote_csv("plain text"), "plain text");
+        assert_eq!(quote_csv("a,b"), "\"a,b\"");
+        assert_eq!(quote_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote_csv("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn delimited_rows() {
+        let page = value(r#"{"items": [{"id": 1, "title": "a,b"}, {"id": 2, "lyrics": "x\ty"}]}"#);
+        let rows = rows(&page, None);
+
+        assert_eq!(delimited(&rows, ','), "id,title,lyrics\n1,\"a,b\",\n2,,x\ty");
+        assert_eq!(delimited(&rows, '\t'), "id\ttitle\tlyrics\n1\ta,b\t\n2\t\tx y");
+    }
+
+    #[test]
+    fn lookup_through_song_wrappers() {
+        let item = value(r#"{"song": {"id": 1, "artist": {"name": "A"}}, "available": [true]}"#);
+
+        assert_eq!(lookup(&item, "id"), Some(&value("1")));
+        assert_eq!(lookup(&item, "song.id"), Some(&value("1")));
+        assert_eq!(lookup(&item, "artist.name"), Some(&value("\"A\"")));
+        assert_eq!(lookup(&item, "available"), Some(&value("[true]")));
+        assert_eq!(lookup(&item, "title"), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tags;
+
+// A pattern matched against a file's path relative to the directory being scanned, or
+// only against its name if the pattern has no `/`. `*` and `?` match anything but `/`,
+// and `**/` matches any number of directories. Case is ignored.
+pub struct Glob {
+    pattern: Vec<char>,
+    is_path: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        Glob {
+            pattern: pattern.to_lowercase().chars().collect(),
+            is_path: pattern.contains('/'),
+        }
+    }
+
+    pub fn is_match(&self, relative_path: &Path) -> bool {
+        let text = if self.is_path {
+            relative_path.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        } else {
+            match relative_path.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => return false,
+            }
+        };
+
+        glob_match(&self.pattern, &text.to_lowercase().chars().collect::<Vec<_>>())
+    }
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    if pattern.starts_with(&['*', '*', '/']) {
+        return (0..text.len() + 1)
+            .filter(|&i| i == 0 || text[i - 1] == '/')
+            .any(|i| glob_match(&pattern[3..], &text[i..]));
+    }
+    if pattern.starts_with(&['*', '*']) {
+        return (0..text.len() + 1).any(|i| glob_match(&pattern[2..], &text[i..]));
+    }
+
+    match (pattern.first(), text.first()) {
+        (Some(&'*'), _) => {
+            (0..text.len() + 1)
+                .take_while(|&i| i == 0 || text[i - 1] != '/')
+                .any(|i| glob_match(&pattern[1..], &text[i..]))
+        }
+        (Some(&'?'), Some(&c)) => c != '/' && glob_match(&pattern[1..], &text[1..]),
+        (Some(p), Some(c)) => p == c && glob_match(&pattern[1..], &text[1..]),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+#[derive(Debug)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub files: Vec<PathBuf>,
+    pub skipped: Vec<Skipped>,
+}
+
+// Finds files in directories, recursively. Without `include` patterns, only files
+// with the extensions in `tags::EXTENSIONS` are found. Files given directly are
+// always included.
+pub struct Scanner {
+    pub include: Vec<Glob>,
+    pub exclude: Vec<Glob>,
+}
+
+impl Scanner {
+    pub fn scan<'p, P>(&self, paths: P) -> Scan
+        where P: IntoIterator<Item = &'p str>
+    {
+        let mut scan = Scan::default();
+
+        for path in paths {
+            let path = Path::new(path);
+
+            if path.is_dir() {
+                self.walk(path, path, &mut scan);
+            } else {
+                scan.files.push(path.to_path_buf());
+            }
+        }
+
+        scan
+    }
+
+    fn is_included(&self, relative_path: &Path) -> bool {
+        if self.exclude.iter().any(|glob| glob.is_match(relative_path)) {
+            return false;
+        }
+
+        if self.include.is_empty() {
+            relative_path.extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| tags::EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        } else {
+            self.include.iter().any(|glob| glob.is_match(relative_path))
+        }
+    }
+
+    // Entries are visited in order of name. Symlinked directories aren't followed,
+    // so that links back to a parent directory don't cause a loop.
+    fn walk(&self, root: &Path, dir: &Path, scan: &mut Scan) {
+        let entries = fs::read_dir(dir)
+            .and_then(|entries| entries.collect::<io::Result<Vec<_>>>());
+        let mut entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                scan.skipped.push(Skipped {
+                    path: dir.to_path_buf(),
+                    reason: format!("failed to read directory: {}", e),
+                });
+                return;
+            }
+        };
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let relative_path = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            let is_symlink = fs::symlink_metadata(&path)
+                .map(|metadata| metadata.file_type().is_symlink())
+                .unwrap_or(false);
+
+            if path.is_dir() {
+                let is_excluded = self.exclude.iter().any(|glob| glob.is_match(&relative_path));
+                if !is_symlink && !is_excluded {
+                    self.walk(root, &path, scan);
+                }
+            } else if self.is_included(&relative_path) {
+                scan.files.push(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).is_match(Path::new(path))
+    }
+
+    #[test]
+    fn file_name_patterns_ignore_case() {
+        assert!(is_match("*.flac", "Artist/Album/Song.FLAC"));
+        assert!(is_match("SONG?.mp3", "song1.mp3"));
+        assert!(!is_match("*.mp3", "song.mp3.bak"));
+    }
+
+    #[test]
+    fn star_stays_within_a_directory() {
+        assert!(is_match("*/song.mp3", "artist/song.mp3"));
+        assert!(!is_match("*/song.mp3", "artist/album/song.mp3"));
+        assert!(!is_match("a?c/*", "a/c/song.mp3"));
+    }
+
+    #[test]
+    fn double_star_spans_directories() {
+        assert!(is_match("**/live/*.mp3", "live/song.mp3"));
+        assert!(is_match("**/live/*.mp3", "artist/album/live/song.mp3"));
+        assert!(!is_match("**/live/*.mp3", "artist/notlive/song.mp3"));
+        assert!(!is_match("**/live/*.mp3", "live/album/song.mp3"));
+        assert!(is_match("Music/**", "music/artist/song.mp3"));
+    }
+}
@@ -5,10 +5,14 @@
 extern crate clap;
 #[macro_use]
 extern crate error_chain;
+#[macro_use]
+extern crate serde_derive;
 
 extern crate clubdarn;
 extern crate serde;
 extern crate serde_json;
+extern crate toml;
+extern crate unicode_width;
 
 #[cfg(feature = "library")]
 extern crate id3;
@@ -16,6 +20,16 @@ extern crate id3;
 pub mod error;
 mod subcommand;
 mod app;
+mod config;
+mod flatten;
+#[cfg(feature = "library")]
+mod library;
+mod playlist;
+#[cfg(feature = "library")]
+mod report;
+mod table;
+#[cfg(feature = "library")]
+mod tags;
 
 use error::*;
 
@@ -43,12 +57,18 @@ fn main() {
 
 fn run() -> Result<()> {
     let matches = app::root().get_matches();
+    let config = config::Config::from_matches(&matches)?;
 
     match matches.subcommand() {
-        ("song", Some(matches)) => subcommand::song::run(matches),
-        ("series", Some(matches)) => subcommand::series::run(matches),
-        ("artist", Some(matches)) => subcommand::artist::run(matches),
-        ("category", Some(matches)) => subcommand::category::run(matches),
+        ("song", Some(matches)) => subcommand::song::run(matches, &config),
+        ("series", Some(matches)) => subcommand::series::run(matches, &config),
+        ("artist", Some(matches)) => subcommand::artist::run(matches, &config),
+        ("category", Some(matches)) => subcommand::category::run(matches, &config),
+        ("compare", Some(matches)) => subcommand::compare::run(matches, &config),
+        ("snapshot", Some(matches)) => subcommand::snapshot::run(matches, &config),
+        ("diff", Some(matches)) => subcommand::diff::run(matches, &config),
+        ("ranking", Some(matches)) => subcommand::ranking::run(matches, &config),
+        ("interactive", Some(matches)) => subcommand::interactive::run(matches, &config),
         (other, _) => Err(format!("unrecognized subcommand {}", other))?,
     }
 }
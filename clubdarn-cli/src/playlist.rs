@@ -0,0 +1,372 @@
+use clubdarn::TitleAndArtist;
+use error::*;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+#[cfg(feature = "library")]
+use tags;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    M3u,
+    Pls,
+    Csv,
+}
+
+pub const KINDS: [&'static str; 3] = ["m3u", "pls", "csv"];
+
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "m3u" | "m3u8" => Ok(Kind::M3u),
+            "pls" => Ok(Kind::Pls),
+            "csv" => Ok(Kind::Csv),
+            other => Err(format!("unknown playlist type {}", other).into()),
+        }
+    }
+}
+
+impl Kind {
+    pub fn from_path(path: &str) -> Result<Self> {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) => ext.parse(),
+            None => Err(format!("can't tell the playlist type of {} (try --type)", path).into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    // Position in the playlist, starting from 1
+    pub position: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl Entry {
+    pub fn title_and_artist(&self) -> Option<TitleAndArtist> {
+        match (self.title.as_ref(), self.artist.as_ref()) {
+            (Some(title), Some(artist)) => {
+                Some(TitleAndArtist {
+                    title: title.as_str().into(),
+                    artist: artist.as_str().into(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn read(path: &str, kind: Kind) -> Result<Vec<Entry>> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .chain_err(|| format!("failed to read {}", path))?;
+
+    let contents = decode(path, bytes)?;
+    let contents = contents.trim_left_matches('\u{feff}');
+
+    // Paths in playlists are relative to the playlist itself
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+
+    match kind {
+        Kind::M3u => Ok(read_m3u(contents, dir)),
+        Kind::Pls => Ok(read_pls(contents, dir)),
+        Kind::Csv => read_csv(contents),
+    }
+}
+
+// Playlists have to be UTF-8. M3U files (unlike M3U8) are often in a legacy encoding
+// such as Shift_JIS instead, which would otherwise turn into garbled titles.
+fn decode(path: &str, bytes: Vec<u8>) -> Result<String> {
+    match String::from_utf8(bytes) {
+        Ok(contents) => Ok(contents),
+        Err(e) => {
+            let valid_up_to = e.utf8_error().valid_up_to();
+            let line = e.into_bytes()[..valid_up_to].iter().filter(|&&b| b == b'\n').count() + 1;
+
+            Err(format!("{} isn't UTF-8 (line {}). Convert it first, e.g., with \
+                         `iconv -f CP932 -t UTF-8` for Shift_JIS.",
+                        path,
+                        line)
+                .into())
+        }
+    }
+}
+
+// Splits "Artist - Title" (as used in `#EXTINF` lines, PLS titles and file names)
+fn split_artist_title(input: &str) -> Option<(String, String)> {
+    [" - ", " – ", " — "]
+        .iter()
+        .filter_map(|separator| input.find(*separator).map(|index| (index, separator.len())))
+        .min()
+        .map(|(index, len)| {
+            (input[..index].trim().to_string(), input[index + len..].trim().to_string())
+        })
+}
+
+// Tags from the file itself, falling back to a file name like "Artist - Title.mp3"
+fn resolve_file(location: &str, dir: &Path) -> Option<(String, String)> {
+    let is_url = location.contains("://");
+    let path = dir.join(location);
+
+    let tags = if is_url { None } else { read_tags(&path) };
+
+    tags.or_else(|| {
+        Path::new(location)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(split_artist_title)
+    })
+}
+
+#[cfg(feature = "library")]
+fn read_tags(path: &Path) -> Option<(String, String)> {
+    tags::read(path)
+        .and_then(|tags| tags.title_and_artist())
+        .map(|(title, artist)| (artist, title))
+        .ok()
+}
+
+#[cfg(not(feature = "library"))]
+fn read_tags(_path: &Path) -> Option<(String, String)> {
+    None
+}
+
+fn entry(position: usize, path: Option<String>, artist_title: Option<(String, String)>) -> Entry {
+    let (artist, title) = match artist_title {
+        Some((artist, title)) => (Some(artist), Some(title)),
+        None => (None, None),
+    };
+
+    Entry {
+        position: position,
+        path: path,
+        title: title,
+        artist: artist,
+    }
+}
+
+// Each file may be preceded by `#EXTINF:<duration>,<artist> - <title>`
+fn read_m3u(contents: &str, dir: &Path) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut extinf = None;
+
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with("#EXTINF:") {
+            extinf = line.find(',').and_then(|index| split_artist_title(&line[index + 1..]));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let position = entries.len() + 1;
+            let artist_title = extinf.take().or_else(|| resolve_file(line, dir));
+            entries.push(entry(position, Some(line.to_string()), artist_title));
+        }
+    }
+
+    entries
+}
+
+// Entries are numbered keys, e.g., `File1=song.mp3` and `Title1=Artist - Title`
+fn read_pls(contents: &str, dir: &Path) -> Vec<Entry> {
+    let mut files: BTreeMap<u32, (Option<&str>, Option<&str>)> = BTreeMap::new();
+
+    for line in contents.lines().map(str::trim) {
+        let (key, value) = match line.find('=') {
+            Some(index) => (&line[..index], line[index + 1..].trim()),
+            None => continue,
+        };
+
+        let key = key.to_lowercase();
+        let (field, number) = if key.starts_with("file") {
+            ("file", &key[4..])
+        } else if key.starts_with("title") {
+            ("title", &key[5..])
+        } else {
+            continue;
+        };
+
+        if let Ok(number) = number.parse::<u32>() {
+            let file = files.entry(number).or_insert((None, None));
+            if field == "file" {
+                file.0 = Some(value);
+            } else {
+                file.1 = Some(value);
+            }
+        }
+    }
+
+    files.values()
+        .enumerate()
+        .map(|(index, &(file, title))| {
+            let artist_title = title.and_then(split_artist_title)
+                .or_else(|| file.and_then(|file| resolve_file(file, dir)));
+            entry(index + 1, file.map(|f| f.to_string()), artist_title)
+        })
+        .collect()
+}
+
+// Records of fields, allowing for quoted fields with commas, quotes ("") and newlines
+pub fn csv_records(contents: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => record.push(::std::mem::replace(&mut field, String::new())),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                record.push(::std::mem::replace(&mut field, String::new()));
+                records.push(::std::mem::replace(&mut record, Vec::new()));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records.retain(|record| record.iter().any(|field| !field.trim().is_empty()));
+    records
+}
+
+const TITLE_COLUMNS: [&'static str; 5] = ["title", "song", "song title", "track", "track name"];
+const ARTIST_COLUMNS: [&'static str; 4] = ["artist", "artists", "artist name", "artist name(s)"];
+
+// The first record is a header, which should have title and artist columns
+fn read_csv(contents: &str) -> Result<Vec<Entry>> {
+    let records = csv_records(contents);
+
+    let header = match records.first() {
+        Some(header) => header.iter().map(|name| name.trim().to_lowercase()).collect::<Vec<_>>(),
+        None => return Ok(Vec::new()),
+    };
+    let column = |names: &[&str]| {
+        header.iter().position(|name| names.iter().any(|n| *n == name.as_str()))
+    };
+
+    let (title_column, artist_column) = match (column(&TITLE_COLUMNS), column(&ARTIST_COLUMNS)) {
+        (Some(title), Some(artist)) => (title, artist),
+        _ => Err("expected title and artist columns in the CSV header")?,
+    };
+
+    let entries = records.iter()
+        .skip(1)
+        .enumerate()
+        .map(|(index, record)| {
+            let field = |index: usize| {
+                record.get(index)
+                    .map(|value| value.trim().to_string())
+                    .and_then(|value| if value.is_empty() { None } else { Some(value) })
+            };
+
+            Entry {
+                position: index + 1,
+                path: None,
+                title: field(title_column),
+                artist: field(artist_column),
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nothing exists here, so artists and titles come from the playlist or file names
+    fn dir() -> &'static Path {
+        Path::new("/nonexistent")
+    }
+
+    fn artist_title(entry: &Entry) -> (Option<&str>, Option<&str>) {
+        (entry.artist.as_ref().map(String::as_str), entry.title.as_ref().map(String::as_str))
+    }
+
+    #[test]
+    fn split_on_first_separator() {
+        assert_eq!(split_artist_title("Artist - Title"),
+                   Some(("Artist".to_string(), "Title".to_string())));
+        assert_eq!(split_artist_title("Artist – Title - Remix"),
+                   Some(("Artist".to_string(), "Title - Remix".to_string())));
+        assert_eq!(split_artist_title("Artist-Title"), None);
+    }
+
+    #[test]
+    fn m3u_extinf_and_file_names() {
+        let contents = "#EXTM3U\n\
+                        #EXTINF:240,Artist A - Title A\n\
+                        music/a.mp3\n\
+                        \n\
+                        # A comment\n\
+                        music/Artist B - Title B.mp3\n\
+                        #EXTINF:180,Untitled\n\
+                        music/c.mp3\n";
+
+        let entries = read_m3u(contents, dir());
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, Some("music/a.mp3".to_string()));
+        assert_eq!(artist_title(&entries[0]), (Some("Artist A"), Some("Title A")));
+        assert_eq!(artist_title(&entries[1]), (Some("Artist B"), Some("Title B")));
+        assert_eq!(entries[2].position, 3);
+        assert_eq!(artist_title(&entries[2]), (None, None));
+    }
+
+    #[test]
+    fn pls_in_numbered_order() {
+        let contents = "[playlist]\n\
+                        File2=b.mp3\n\
+                        Title2=Artist B - Title B\n\
+                        file1=Artist A - Title A.ogg\n\
+                        NumberOfEntries=2\n";
+
+        let entries = read_pls(contents, dir());
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, Some("Artist A - Title A.ogg".to_string()));
+        assert_eq!(artist_title(&entries[0]), (Some("Artist A"), Some("Title A")));
+        assert_eq!(entries[1].path, Some("b.mp3".to_string()));
+        assert_eq!(artist_title(&entries[1]), (Some("Artist B"), Some("Title B")));
+    }
+
+    #[test]
+    fn csv_quoted_fields() {
+        let contents = "title,artist\r\n\
+                        \"Hello, World\",\"The \"\"Band\"\"\"\r\n\
+                        \r\n\
+                        \"Two\nLines\",Artist";
+
+        assert_eq!(csv_records(contents),
+                   vec![vec!["title".to_string(), "artist".to_string()],
+                        vec!["Hello, World".to_string(), "The \"Band\"".to_string()],
+                        vec!["Two\nLines".to_string(), "Artist".to_string()]]);
+    }
+
+    #[test]
+    fn rejects_other_encodings() {
+        // "テスト" in Shift_JIS on the second line
+        let bytes = b"#EXTM3U\n\x83\x65\x83\x58\x83\x67.mp3\n".to_vec();
+        let error = decode("test.m3u", bytes).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+
+        assert_eq!(decode("test.m3u", "テスト.mp3".as_bytes().to_vec()).unwrap(),
+                   "テスト.mp3");
+    }
+}
@@ -0,0 +1,221 @@
+// A CSV report of how each file in a music library was matched to a song. Rows are
+// written in batches as files are matched, so an interrupted run can be resumed.
+// Paths are written in canonical form, so a resumed run can be started from
+// another directory.
+
+use clubdarn::{Client, TitleAndArtist};
+use clubdarn::matching::Match;
+use error::*;
+use flatten;
+use library::Skipped;
+use playlist;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Files are matched and written this many at a time
+const BATCH_SIZE: usize = 50;
+
+const HEADER: [&'static str; 9] = ["path",
+                                   "tagTitle",
+                                   "tagArtist",
+                                   "songId",
+                                   "songTitle",
+                                   "songArtist",
+                                   "method",
+                                   "confidence",
+                                   "error"];
+
+const PATH_COLUMN: usize = 0;
+const SONG_ID_COLUMN: usize = 3;
+
+pub struct Report {
+    path: String,
+    writer: BufWriter<File>,
+    // Paths of files that were matched in a previous run
+    matched: HashSet<String>,
+}
+
+impl Report {
+    // With `resume`, rows of files that were already matched are kept, and those files
+    // can be skipped (see `matched`). Other rows are dropped, so those files are retried.
+    // The kept rows are written to a temporary file that then replaces the report, so
+    // they aren't lost if this run is interrupted too.
+    pub fn create(path: &str, resume: bool) -> Result<Self> {
+        let mut kept = if resume && Path::new(path).exists() {
+            read_matched(path)?
+        } else {
+            Vec::new()
+        };
+
+        for record in &mut kept {
+            let path = canonical(Path::new(&record[PATH_COLUMN]));
+            record[PATH_COLUMN] = path;
+        }
+
+        let temp_path = format!("{}.tmp", path);
+        write_records(&temp_path, &kept)?;
+        fs::rename(&temp_path, path)
+            .chain_err(|| format!("failed to replace {} with {}", path, temp_path))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .chain_err(|| format!("failed to open {}", path))?;
+
+        Ok(Report {
+            path: path.to_string(),
+            writer: BufWriter::new(file),
+            matched: kept.into_iter().map(|mut record| record.swap_remove(PATH_COLUMN)).collect(),
+        })
+    }
+
+    pub fn is_matched(&self, path: &Path) -> bool {
+        self.matched.contains(&canonical(path))
+    }
+
+    // Matches the files' titles and artists, writing a row for each file whether or not
+    // a song was found. Prints progress and a summary of the match methods on stderr.
+    pub fn add_files(&mut self,
+                     client: &Client,
+                     files: &[PathBuf],
+                     queries: &[TitleAndArtist],
+                     skipped: &[Skipped])
+                     -> Result<()> {
+        for file in skipped {
+            self.add(&file.path, None, None, Some(&file.reason))?;
+        }
+        self.flush()?;
+
+        let stderr = &mut io::stderr();
+        let mut methods = BTreeMap::new();
+        let mut unmatched = 0;
+        let mut done = 0;
+
+        for (paths, batch) in files.chunks(BATCH_SIZE).zip(queries.chunks(BATCH_SIZE)) {
+            let found = client.match_titles_and_artists(batch, None)?;
+
+            for ((path, query), found) in paths.iter().zip(batch).zip(found) {
+                match found {
+                    Some(ref found) => *methods.entry(found.method.name()).or_insert(0) += 1,
+                    None => unmatched += 1,
+                }
+                self.add(path, Some(query), found.as_ref(), None)?;
+            }
+
+            // Flushing after each batch means a resumed run only repeats the last batch
+            self.flush()?;
+            done += paths.len();
+            writeln!(stderr, "matched {} of {} files", done, files.len())
+                .chain_err(|| "failed to write to stderr")?;
+        }
+
+        let mut summary = methods.iter()
+            .map(|(method, count)| format!("{} {}", count, method))
+            .collect::<Vec<_>>();
+        summary.push(format!("{} unmatched", unmatched));
+        if !self.matched.is_empty() {
+            summary.push(format!("{} already matched", self.matched.len()));
+        }
+
+        writeln!(stderr, "{}: {}", self.path, summary.join(", "))
+            .chain_err(|| "failed to write to stderr")
+    }
+
+    fn add(&mut self,
+           path: &Path,
+           query: Option<&TitleAndArtist>,
+           found: Option<&Match>,
+           error: Option<&str>)
+           -> Result<()> {
+        let mut record = vec![canonical(path)];
+
+        match query {
+            Some(query) => record.extend(vec![query.title.to_string(), query.artist.to_string()]),
+            None => record.extend(vec![String::new(); 2]),
+        }
+
+        match found {
+            Some(found) => {
+                record.extend(vec![found.song.id.0.to_string(),
+                                   found.song.title.clone(),
+                                   found.song.artist.name.clone(),
+                                   found.method.to_string(),
+                                   format!("{:.2}", found.confidence)])
+            }
+            None => record.extend(vec![String::new(); 5]),
+        }
+
+        record.push(error.unwrap_or("").to_string());
+        self.write_record(&record)
+    }
+
+    fn write_record(&mut self, record: &[String]) -> Result<()> {
+        write_record(&mut self.writer, record)
+            .chain_err(|| format!("failed to write to {}", self.path))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush().chain_err(|| format!("failed to write to {}", self.path))
+    }
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &[String]) -> io::Result<()> {
+    let line = record.iter().map(|field| flatten::quote_csv(field)).collect::<Vec<_>>();
+    writeln!(writer, "{}", line.join(","))
+}
+
+// Writes a new report with the header and the given records
+fn write_records(path: &str, records: &[Vec<String>]) -> Result<()> {
+    let file = File::create(path).chain_err(|| format!("failed to create {}", path))?;
+    let mut writer = BufWriter::new(file);
+
+    let header = HEADER.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+    let mut write = || -> io::Result<()> {
+        write_record(&mut writer, &header)?;
+        for record in records {
+            write_record(&mut writer, record)?;
+        }
+        writer.flush()
+    };
+
+    write().chain_err(|| format!("failed to write to {}", path))
+}
+
+// The absolute path with symlinks resolved, or the path as given if it can't be
+// resolved (e.g., it no longer exists)
+fn canonical(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Rows of an existing report that have a song ID
+fn read_matched(path: &str) -> Result<Vec<Vec<String>>> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .chain_err(|| format!("failed to read {}", path))?;
+
+    let mut records = playlist::csv_records(&contents).into_iter();
+
+    match records.next() {
+        Some(ref header) if header.iter().map(String::as_str).eq(HEADER.iter().cloned()) => {}
+        Some(_) => Err(format!("{} isn't a library report", path))?,
+        None => return Ok(Vec::new()),
+    }
+
+    let matched = records.filter_map(|mut record| {
+            record.resize(HEADER.len(), String::new());
+            if record[SONG_ID_COLUMN].is_empty() {
+                None
+            } else {
+                Some(record)
+            }
+        })
+        .collect();
+
+    Ok(matched)
+}
@@ -2,6 +2,8 @@ use app;
 use app::AppExt;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use clubdarn;
+use clubdarn::catalogue;
+use config::Config;
 use error::*;
 
 pub fn app() -> App<'static, 'static> {
@@ -21,17 +23,43 @@ pub fn app() -> App<'static, 'static> {
         .about("Find artists with live performances")
         .with_global_args();
 
+    let id = SubCommand::with_name("id")
+        .about("Find an artist by ID, along with a page of their songs")
+        .arg(Arg::with_name("artist-id")
+            .help("Artist ID")
+            .value_name("ARTIST_ID")
+            .required(true))
+        .with_global_args();
+
+    let songs = SubCommand::with_name("songs")
+        .about("List an artist's songs")
+        .arg(Arg::with_name("artist-id")
+            .help("Artist ID")
+            .value_name("ARTIST_ID")
+            .required(true))
+        .arg(Arg::with_name("all")
+            .help("List every song, including live performances, across all pages")
+            .long("all"))
+        .with_global_args();
+
     SubCommand::with_name("artist")
         .about("Find artists")
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .subcommand(name)
         .subcommand(live)
+        .subcommand(id)
+        .subcommand(songs)
 }
 
-pub fn run(matches: &ArgMatches) -> Result<()> {
-    let context = app::Context::from_matches(matches)?;
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
     let artists = context.client.artists();
 
+    match matches.subcommand_name() {
+        Some("name") | None => {}
+        Some(other) => context.require_online(&format!("artist {}", other))?,
+    }
+
     let mut request = match matches.subcommand() {
         ("name", Some(matches)) => {
             let match_type = if matches.is_present("starts-with") {
@@ -40,13 +68,37 @@ pub fn run(matches: &ArgMatches) -> Result<()> {
                 clubdarn::MatchType::Contains
             };
 
-            artists.by_name(matches.value_of("query").unwrap(), match_type)
+            let query = matches.value_of("query").unwrap();
+            let pages = catalogue::pages(context.page, |page| {
+                context.catalogue().artists_by_name(query, match_type, page)
+            });
+            return context.print_pages(pages);
         }
         ("live", Some(_)) => artists.live_performance(),
+        // These use a different response type, so we return early here
+        ("id", Some(matches)) => {
+            let artist_id = value_t!(matches, "artist-id", u32)?;
+            let id = clubdarn::ArtistId(artist_id);
+
+            match context.client.artist_by_id(id, context.page, None)? {
+                Some(result) => return context.printer.stdout(&result),
+                None => Err(format!("no artist found with ID {}", artist_id))?,
+            }
+        }
+        ("songs", Some(matches)) => {
+            let artist_id = value_t!(matches, "artist-id", u32)?;
+
+            if matches.is_present("all") {
+                let id = clubdarn::ArtistId(artist_id);
+                let result = context.client.artist_discography(id, None, None)?;
+                return context.printer.stdout(&result);
+            } else {
+                let mut request = context.client.songs().by_artist_id(artist_id);
+                return context.print_pages(request.set_page(context.page).pages());
+            }
+        }
         (other, _) => Err(format!("unrecognized subcommand {}", other))?,
     };
 
-    let result = request.set_page(context.page).send()?;
-
-    context.printer.stdout(&result)
+    context.print_pages(request.set_page(context.page).pages())
 }
@@ -1,6 +1,7 @@
 use app;
 use app::AppExt;
 use clap::{App, Arg, ArgMatches, SubCommand};
+use config::Config;
 use error::*;
 
 pub fn app() -> App<'static, 'static> {
@@ -13,19 +14,19 @@ pub fn app() -> App<'static, 'static> {
         .with_global_args()
 }
 
-pub fn run(matches: &ArgMatches) -> Result<()> {
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
     use clubdarn::category::*;
 
-    let context = app::Context::from_matches(matches)?;
+    let context = app::Context::from_matches(matches, config)?;
     let p = context.printer;
 
     let category_type = matches.value_of("category-type").unwrap();
 
     match category_type {
-        "new" => return p.stdout(&new_songs::CATEGORIES),
-        "series" => return p.stdout(&series::CATEGORIES),
-        "vocaloid" => return p.stdout(&vocaloid::CATEGORIES),
-        "ranking" => return p.stdout(&ranking::CATEGORIES),
+        "new" => return p.stdout(&new_songs::CATEGORIES[..]),
+        "series" => return p.stdout(&series::CATEGORIES[..]),
+        "vocaloid" => return p.stdout(&vocaloid::CATEGORIES[..]),
+        "ranking" => return p.stdout(&ranking::CATEGORIES[..]),
         other => Err(format!("unknown category {}", other))?,
     };
 
@@ -0,0 +1,92 @@
+use app;
+use app::AppExt;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use clubdarn;
+use config::Config;
+use error::*;
+
+pub fn app() -> App<'static, 'static> {
+    let id = SubCommand::with_name("id")
+        .about("Compare songs with ID")
+        .arg(Arg::with_name("song-id")
+            .help("ID without hyphen (e.g., 360715)")
+            .value_name("SONG_ID")
+            .multiple(true)
+            .required(true))
+        .with_global_args();
+
+    let title = SubCommand::with_name("title")
+        .about("Compare songs found by title")
+        .arg(Arg::with_name("starts-with")
+            .help("Require that the match occurs at the beginning of the song title")
+            .long("starts-with"))
+        .arg(Arg::with_name("query")
+            .help("The query to match on")
+            .value_name("QUERY")
+            .multiple(true)
+            .empty_values(false)
+            .required(true))
+        .with_global_args();
+
+    let song = SubCommand::with_name("song")
+        .about("Compare songs")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(id)
+        .subcommand(title);
+
+    SubCommand::with_name("compare")
+        .about("Compare song availability across karaoke machines")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("serial")
+            .help("Serial number or machine profile name to compare")
+            .long("serial")
+            .value_name("SERIAL_NO")
+            .multiple(true)
+            .number_of_values(1)
+            .required(true))
+        .subcommand(song)
+}
+
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
+    context.require_online("compare")?;
+
+    // Resolve machine profiles before building the request, since the
+    // request can't outlive the serial numbers it's sent with
+    let serial_nos = matches.values_of("serial")
+        .unwrap()
+        .map(|s| context.client.machine(s).map(|machine| machine.serial_no))
+        .collect::<clubdarn::Result<Vec<_>>>()?;
+    let serial_nos = serial_nos.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+    let songs = context.client.songs();
+    let query: String;
+
+    let result = match matches.subcommand() {
+        ("song", Some(matches)) => {
+            match matches.subcommand() {
+                ("id", Some(matches)) => {
+                    let ids = values_t!(matches, "song-id", u32)?;
+                    songs.by_ids(&ids).compare_serial_nos(&serial_nos)?
+                }
+                ("title", Some(matches)) => {
+                    let match_type = if matches.is_present("starts-with") {
+                        clubdarn::MatchType::StartsWith
+                    } else {
+                        clubdarn::MatchType::Contains
+                    };
+
+                    query = matches.values_of("query").unwrap().collect::<Vec<_>>().join(" ");
+
+                    let mut request = songs.by_title(&query, match_type);
+                    request.set_page(context.page);
+                    context.client.compare_request(&mut request, &serial_nos)?
+                }
+                (other, _) => Err(format!("unrecognized subcommand {}", other))?,
+            }
+        }
+        (other, _) => Err(format!("unrecognized subcommand {}", other))?,
+    };
+
+    context.printer.stdout(&result)
+}
@@ -0,0 +1,85 @@
+use app;
+use app::AppExt;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use clubdarn::diff::Diff;
+use clubdarn::snapshot::Snapshot;
+use config::Config;
+use error::*;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use subcommand::snapshot;
+
+pub fn app() -> App<'static, 'static> {
+    SubCommand::with_name("diff")
+        .about("Compare two snapshots for added, removed, and changed songs")
+        .arg(Arg::with_name("old")
+            .help("Snapshot file created by the `snapshot` subcommand")
+            .value_name("OLD")
+            .required(true))
+        .arg(Arg::with_name("new")
+            .help("Snapshot file to compare against. If omitted, the categories in the old \
+                   snapshot are fetched again.")
+            .value_name("NEW"))
+        .arg(Arg::with_name("within")
+            .help("List songs whose end date is within this many days")
+            .long("within")
+            .value_name("DAYS")
+            .default_value("30"))
+        .arg(Arg::with_name("check-removed")
+            .help("Look up songs that left the snapshot's categories by ID, to tell which \
+                   ones were removed")
+            .long("check-removed"))
+        .with_global_args()
+}
+
+fn read_snapshot(path: &str) -> Result<Snapshot> {
+    let file = File::open(path).chain_err(|| format!("failed to open {}", path))?;
+    Snapshot::read(BufReader::new(file))
+        .chain_err(|| format!("failed to read snapshot from {}", path))
+}
+
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
+    let within = value_t!(matches, "within", u32)?;
+
+    let old = read_snapshot(matches.value_of("old").unwrap())?;
+
+    let new = match matches.value_of("new") {
+        Some(path) => read_snapshot(path)?,
+        None => {
+            context.require_online("diff without a new snapshot")?;
+
+            // Compare against the same machine unless another one was requested
+            let serial_no = match context.client.default_serial_no() {
+                Some(serial_no) => Some(serial_no.to_string()),
+                None => old.header.serial_no.clone(),
+            };
+            let category_ids = old.header.categories.iter().map(|c| c.as_str()).collect::<Vec<_>>();
+
+            snapshot::fetch(&context.client,
+                            &category_ids,
+                            serial_no.as_ref().map(|s| s.as_str()))?
+        }
+    };
+
+    let mut diff = Diff::between(&old, &new, within);
+
+    if let Some(ref serial_no) = diff.serial_no {
+        let name = |serial_no: &Option<String>| {
+            serial_no.as_ref().map_or("no serial number".to_string(), |s| s.clone())
+        };
+        writeln!(&mut io::stderr(),
+                 "warning: comparing snapshots from different machines ({} and {})",
+                 name(&serial_no.old),
+                 name(&serial_no.new))
+            .chain_err(|| "failed to write to stderr")?;
+    }
+
+    if matches.is_present("check-removed") {
+        context.require_online("diff --check-removed")?;
+        let serial_no = new.header.serial_no.as_ref().map(|s| s.as_str());
+        context.client.confirm_removed(&mut diff, serial_no)?;
+    }
+
+    context.printer.stdout(&diff)
+}
@@ -0,0 +1,304 @@
+use app;
+use app::AppExt;
+use clap::{App, ArgMatches, SubCommand};
+use clubdarn::{Artist, MatchType, Paginated, Song};
+use clubdarn::category::{self, series};
+use config::Config;
+use error::*;
+use serde::Serialize;
+use serde_json;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use table::{self, Tabular};
+
+const HELP: &'static str = "\
+Commands:
+  song QUERY     Find songs by title
+  artist QUERY   Find artists by name
+  next, prev     Show the next or previous page of results
+  songs N        List songs by artist N, or by the artist of song N
+  series N       List songs in the same series as song N
+  similar N      List songs similar to song N
+  show N         Show result N in the output format given by --format
+  add N          Add song N to the setlist
+  remove N       Remove song N from the setlist
+  setlist        Show the setlist
+  save FILE      Save the setlist as JSON
+  help           Show this message
+  quit           Exit";
+
+pub fn app() -> App<'static, 'static> {
+    SubCommand::with_name("interactive")
+        .about("Browse songs and artists interactively, building up a setlist")
+        .with_global_args()
+}
+
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
+    let mut browser = Browser {
+        context: &context,
+        current: None,
+        setlist: Vec::new(),
+    };
+
+    println!("Type `help` for a list of commands");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("clubdarn> ");
+        io::stdout().flush().chain_err(|| "failed to write to stdout")?;
+
+        let line = match lines.next() {
+            Some(line) => line.chain_err(|| "failed to read from stdin")?,
+            None => break,
+        };
+
+        // Errors are reported without leaving the browser
+        match browser.execute(line.trim()) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                let causes = e.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                writeln!(&mut io::stderr(), "error: {}", causes.join(": "))
+                    .chain_err(|| "failed to write to stderr")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// What the current results were fetched for, so that other pages can be fetched
+enum Query {
+    SongTitle(String),
+    ArtistName(String),
+    ArtistSongs(u32),
+    Series(String, &'static str),
+    Similar(u32),
+}
+
+enum Results {
+    Songs(Paginated<Song>),
+    Artists(Paginated<Artist>),
+}
+
+impl Results {
+    fn page(&self, forward: bool) -> Option<u32> {
+        match (self, forward) {
+            (&Results::Songs(ref page), true) => page.next_page(),
+            (&Results::Songs(ref page), false) => page.prev_page(),
+            (&Results::Artists(ref page), true) => page.next_page(),
+            (&Results::Artists(ref page), false) => page.prev_page(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match *self {
+            Results::Songs(ref page) => page.items.is_empty(),
+            Results::Artists(ref page) => page.items.is_empty(),
+        }
+    }
+}
+
+struct Browser<'c, 'a: 'c> {
+    context: &'c app::Context<'a>,
+    current: Option<(Query, Results)>,
+    setlist: Vec<Song>,
+}
+
+impl<'c, 'a> Browser<'c, 'a> {
+    // Returns `false` if the browser should exit
+    fn execute(&mut self, line: &str) -> Result<bool> {
+        let (command, arg) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], line[index..].trim()),
+            None => (line, ""),
+        };
+
+        match command {
+            "" => {}
+            "song" | "s" => self.search(Query::SongTitle(query(arg)?))?,
+            "artist" | "a" => self.search(Query::ArtistName(query(arg)?))?,
+            "next" | "n" => self.turn_page(true)?,
+            "prev" | "p" => self.turn_page(false)?,
+            "songs" => {
+                let artist_id = match self.current {
+                    Some((_, Results::Artists(ref page))) => item(&page.items, arg)?.id.0,
+                    _ => self.song(arg)?.artist.id.0,
+                };
+                self.search(Query::ArtistSongs(artist_id))?;
+            }
+            "series" => {
+                let title = match self.song(arg)?.series {
+                    Some(ref title) => title.clone(),
+                    None => Err(format!("song {} isn't part of a series", arg))?,
+                };
+                self.search_series(title)?;
+            }
+            "similar" => {
+                self.context.require_online("similar")?;
+                let song_id = self.song(arg)?.id.0;
+                self.search(Query::Similar(song_id))?;
+            }
+            "show" => {
+                match self.current {
+                    Some((_, Results::Artists(ref page))) => {
+                        self.context.printer.stdout(item(&page.items, arg)?)?
+                    }
+                    _ => self.context.printer.stdout(self.song(arg)?)?,
+                }
+            }
+            "add" => {
+                let song = self.song(arg)?.clone();
+                println!("Added {} to the setlist ({} songs)",
+                         song.title,
+                         self.setlist.len() + 1);
+                self.setlist.push(song);
+            }
+            "remove" => {
+                let index = index(arg, self.setlist.len())?;
+                let song = self.setlist.remove(index);
+                println!("Removed {} from the setlist", song.title);
+            }
+            "setlist" => show(&self.setlist)?,
+            "save" => {
+                if arg.is_empty() {
+                    Err("expected a file name")?
+                }
+                let file = File::create(arg).chain_err(|| format!("failed to create {}", arg))?;
+                serde_json::to_writer_pretty(&mut BufWriter::new(file), &self.setlist)
+                    .chain_err(|| format!("failed to write setlist to {}", arg))?;
+                println!("Saved {} songs to {}", self.setlist.len(), arg);
+            }
+            "help" | "?" => println!("{}", HELP),
+            "quit" | "q" | "exit" => return Ok(false),
+            other => Err(format!("unknown command {} (type `help` for help)", other))?,
+        }
+
+        Ok(true)
+    }
+
+    fn fetch(&self, query: &Query, page: u32) -> Result<Results> {
+        let catalogue = self.context.catalogue();
+
+        let results = match *query {
+            Query::SongTitle(ref title) => {
+                Results::Songs(catalogue.songs_by_title(title, MatchType::Contains, page)?)
+            }
+            Query::ArtistName(ref name) => {
+                Results::Artists(catalogue.artists_by_name(name, MatchType::Contains, page)?)
+            }
+            Query::ArtistSongs(artist_id) => {
+                let category_id = category::ARTIST_NAME.id.0;
+                Results::Songs(catalogue.songs_by_artist_in_category_id(artist_id,
+                                                                        category_id,
+                                                                        page)?)
+            }
+            Query::Series(ref title, category_id) => {
+                Results::Songs(catalogue.songs_by_series_in_category_id(title,
+                                                                        category_id,
+                                                                        page)?)
+            }
+            Query::Similar(song_id) => {
+                let mut request = self.context.client.songs().similar_to(song_id);
+                Results::Songs(request.set_page(page).send()?)
+            }
+        };
+
+        Ok(results)
+    }
+
+    fn search(&mut self, query: Query) -> Result<()> {
+        let results = self.fetch(&query, 1)?;
+        show_results(&results)?;
+        self.current = Some((query, results));
+        Ok(())
+    }
+
+    // Songs only know the title of their series, so each series category is tried in turn
+    fn search_series(&mut self, title: String) -> Result<()> {
+        for category in series::CATEGORIES.iter() {
+            let query = Query::Series(title.clone(), category.id.0);
+            let results = self.fetch(&query, 1)?;
+
+            if !results.is_empty() {
+                show_results(&results)?;
+                self.current = Some((query, results));
+                return Ok(());
+            }
+        }
+
+        Err(format!("no songs found in series {}", title).into())
+    }
+
+    fn turn_page(&mut self, forward: bool) -> Result<()> {
+        let results = match self.current {
+            Some((ref query, ref results)) => {
+                match results.page(forward) {
+                    Some(page) => self.fetch(query, page)?,
+                    None if forward => Err("already on the last page")?,
+                    None => Err("already on the first page")?,
+                }
+            }
+            None => Err("no results yet (try `song QUERY` or `artist QUERY`)")?,
+        };
+
+        show_results(&results)?;
+        if let Some((_, ref mut current)) = self.current {
+            *current = results;
+        }
+
+        Ok(())
+    }
+
+    fn song(&self, arg: &str) -> Result<&Song> {
+        match self.current {
+            Some((_, Results::Songs(ref page))) => item(&page.items, arg),
+            Some((_, Results::Artists(_))) => Err("the current results are artists, not songs")?,
+            None => Err("no results yet (try `song QUERY` or `artist QUERY`)")?,
+        }
+    }
+}
+
+fn query(arg: &str) -> Result<String> {
+    if arg.is_empty() {
+        Err("expected a query")?
+    }
+
+    Ok(arg.to_string())
+}
+
+// Items are numbered from 1, as shown in the results
+fn index(arg: &str, len: usize) -> Result<usize> {
+    if len == 0 {
+        Err("there's nothing to choose from")?
+    }
+
+    match arg.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= len => Ok(n - 1),
+        _ => Err(format!("expected a number from 1 to {}", len).into()),
+    }
+}
+
+fn item<'i, T>(items: &'i [T], arg: &str) -> Result<&'i T> {
+    Ok(&items[index(arg, items.len())?])
+}
+
+fn show<T: Serialize + Tabular>(t: &T) -> Result<()> {
+    let value = serde_json::to_value(t).chain_err(|| "failed to serialize JSON")?;
+
+    match table::render_numbered::<T>(&value) {
+        Some(table) => println!("{}", table),
+        None => println!("{}", value),
+    }
+
+    Ok(())
+}
+
+fn show_results(results: &Results) -> Result<()> {
+    match *results {
+        Results::Songs(ref page) => show(page),
+        Results::Artists(ref page) => show(page),
+    }
+}
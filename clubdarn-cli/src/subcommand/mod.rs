@@ -1,4 +1,9 @@
 pub mod artist;
 pub mod category;
+pub mod compare;
+pub mod diff;
+pub mod interactive;
+pub mod ranking;
 pub mod song;
 pub mod series;
+pub mod snapshot;
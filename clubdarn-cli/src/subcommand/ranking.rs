@@ -0,0 +1,90 @@
+use app;
+use app::AppExt;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use clubdarn::category::ranking::CATEGORIES;
+use clubdarn::ranking::History;
+use config::Config;
+use error::*;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Write};
+
+pub fn app() -> App<'static, 'static> {
+    let category_ids = CATEGORIES.iter().map(|c| c.id.0).collect::<Vec<_>>();
+
+    let category_id_arg = Arg::with_name("category-id")
+        .help("Ranking category IDs. Defaults to every ranking category.")
+        .value_name("CATEGORY_ID")
+        .multiple(true)
+        .possible_values(&category_ids);
+
+    let record = SubCommand::with_name("record")
+        .about("Fetch the current rankings and append them to the history file")
+        .arg(category_id_arg.clone())
+        .with_global_args();
+
+    let movements = SubCommand::with_name("movements")
+        .about("Compare the two most recent rankings in the history file")
+        .arg(category_id_arg)
+        .with_global_args();
+
+    SubCommand::with_name("ranking")
+        .about("Track ranking positions over time")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("history")
+            .help("Ranking history file (JSON lines)")
+            .long("history")
+            .value_name("FILE")
+            .required(true))
+        .subcommand(record)
+        .subcommand(movements)
+}
+
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
+    let path = matches.value_of("history").unwrap();
+
+    let (subcommand, sub_matches) = match matches.subcommand() {
+        (subcommand, Some(sub_matches)) => (subcommand, sub_matches),
+        (other, None) => Err(format!("unrecognized subcommand {}", other))?,
+    };
+
+    let category_ids = match sub_matches.values_of("category-id") {
+        Some(ids) => ids.collect::<Vec<_>>(),
+        None => CATEGORIES.iter().map(|c| c.id.0).collect(),
+    };
+
+    match subcommand {
+        "record" => {
+            context.require_online("ranking record")?;
+
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .chain_err(|| format!("failed to open {}", path))?;
+
+            let stderr = &mut io::stderr();
+
+            for category_id in category_ids {
+                writeln!(stderr, "Fetching ranking {}", category_id)
+                    .chain_err(|| "failed to write to stderr")?;
+
+                let ranking = context.client.ranking(category_id, None)?;
+                History::append(&file, &ranking)?;
+            }
+
+            Ok(())
+        }
+        "movements" => {
+            let file = File::open(path).chain_err(|| format!("failed to open {}", path))?;
+            let history = History::read(BufReader::new(file))?;
+
+            let movements = category_ids.into_iter()
+                .filter_map(|category_id| history.movements(category_id))
+                .collect::<Vec<_>>();
+
+            context.printer.stdout(&movements)
+        }
+        other => Err(format!("unrecognized subcommand {}", other))?,
+    }
+}
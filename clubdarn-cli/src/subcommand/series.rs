@@ -2,7 +2,10 @@ use app;
 use app::AppExt;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use clubdarn::category::series::{ANIME, TOKUSATSU, MUSIC_VIDEO};
+use clubdarn::catalogue;
+use config::Config;
 use error::*;
+use std::io::{self, Write};
 
 pub fn app() -> App<'static, 'static> {
     SubCommand::with_name("series")
@@ -11,19 +14,47 @@ pub fn app() -> App<'static, 'static> {
             .required(true)
             .value_name("SERIES_TYPE")
             .possible_values(&["anime", "tokusatsu", "video"]))
+        .arg(Arg::with_name("songs")
+            .help("Include every song from every series (this sends a lot of requests)")
+            .long("songs"))
         .with_global_args()
 }
 
-pub fn run(matches: &ArgMatches) -> Result<()> {
-    let context = app::Context::from_matches(matches)?;
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
 
-    let query = match matches.value_of("series-type").unwrap() {
-        "anime" => ANIME.id.0,
-        "tokusatsu" => TOKUSATSU.id.0,
-        "video" => MUSIC_VIDEO.id.0,
+    let category = match matches.value_of("series-type").unwrap() {
+        "anime" => ANIME,
+        "tokusatsu" => TOKUSATSU,
+        "video" => MUSIC_VIDEO,
         other => Err(format!("invalid series type {}", other))?,
     };
-    let result = context.client.series().by_category_id(query).set_page(context.page).send()?;
 
-    context.printer.stdout(&result)
+    if matches.is_present("songs") {
+        context.require_online("series --songs")?;
+
+        let stderr = &mut io::stderr();
+        let mut crawler = context.client.crawl_series(category, None)?;
+        let mut result = Vec::with_capacity(crawler.total());
+
+        while let Some(series_songs) = crawler.next() {
+            let series_songs = series_songs?;
+            writeln!(stderr,
+                     "[{}/{}] {}",
+                     crawler.done(),
+                     crawler.total(),
+                     series_songs.series.title)
+                .chain_err(|| "failed to write to stderr")?;
+            result.push(series_songs);
+        }
+
+        return context.printer.stdout(&result);
+    }
+
+    let category_id = category.id.0;
+    let pages = catalogue::pages(context.page, |page| {
+        context.catalogue().series_by_category_id(category_id, page)
+    });
+
+    context.print_pages(pages)
 }
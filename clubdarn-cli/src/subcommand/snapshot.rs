@@ -0,0 +1,128 @@
+use app;
+use app::AppExt;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use clubdarn;
+use clubdarn::category::{self, new_songs, ranking, series, vocaloid};
+use clubdarn::snapshot::Snapshot;
+use config::Config;
+use error::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+pub fn app() -> App<'static, 'static> {
+    SubCommand::with_name("snapshot")
+        .about("Save songs from categories to a snapshot file (JSON lines)")
+        .arg(Arg::with_name("category-type")
+            .help("Category types to include. If neither this nor --category-id is given, \
+                   every category type is included.")
+            .long("type")
+            .value_name("CATEGORY_TYPE")
+            .multiple(true)
+            .number_of_values(1)
+            .possible_values(&["new", "series", "vocaloid", "ranking"]))
+        .arg(Arg::with_name("category-id")
+            .help("Category ID to include")
+            .long("category-id")
+            .value_name("CATEGORY_ID")
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("output")
+            .help("File to write the snapshot to. Defaults to stdout.")
+            .long("output")
+            .short("o")
+            .value_name("FILE"))
+        .with_global_args()
+}
+
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
+    context.require_online("snapshot")?;
+
+    let category_types = match matches.values_of("category-type") {
+        Some(types) => types.collect(),
+        None if matches.is_present("category-id") => vec![],
+        None => vec!["new", "series", "vocaloid", "ranking"],
+    };
+
+    let mut category_ids: Vec<&str> = Vec::new();
+    for category_type in category_types {
+        match category_type {
+            "new" => category_ids.extend(new_songs::CATEGORIES.iter().map(|c| c.id.0)),
+            "series" => category_ids.extend(series::CATEGORIES.iter().map(|c| c.id.0)),
+            "vocaloid" => category_ids.extend(vocaloid::CATEGORIES.iter().map(|c| c.id.0)),
+            "ranking" => category_ids.extend(ranking::CATEGORIES.iter().map(|c| c.id.0)),
+            other => Err(format!("unknown category {}", other))?,
+        }
+    }
+    if let Some(ids) = matches.values_of("category-id") {
+        category_ids.extend(ids);
+    }
+
+    let snapshot = fetch(&context.client, &category_ids, None)?;
+
+    match matches.value_of("output") {
+        Some(path) => {
+            let file = File::create(path).chain_err(|| format!("failed to create {}", path))?;
+            snapshot.write(BufWriter::new(file))?;
+        }
+        None => {
+            let stdout = io::stdout();
+            snapshot.write(stdout.lock())?;
+        }
+    }
+
+    Ok(())
+}
+
+// Fetches every song in each category, reporting progress on stderr.
+// If `serial_no` is `None`, the client's default serial number is used.
+pub fn fetch(client: &clubdarn::Client,
+             category_ids: &[&str],
+             serial_no: Option<&str>)
+             -> Result<Snapshot> {
+    let stderr = &mut io::stderr();
+    let mut snapshot = Snapshot::new(serial_no.or(client.default_serial_no()));
+
+    for (index, category_id) in category_ids.iter().enumerate() {
+        writeln!(stderr,
+                 "[{}/{}] Fetching category {}",
+                 index + 1,
+                 category_ids.len(),
+                 category_id)
+            .chain_err(|| "failed to write to stderr")?;
+
+        // `series_category` also maps song categories (e.g., new anime songs) to series
+        let series_category = if series::CATEGORIES.iter().any(|c| c.id.0 == *category_id) {
+            category::series_category(category_id)
+        } else {
+            None
+        };
+
+        match series_category {
+            Some(series_category) => {
+                let mut crawler = client.crawl_series(series_category, serial_no)?;
+
+                while let Some(series_songs) = crawler.next() {
+                    let series_songs = series_songs?;
+                    writeln!(stderr,
+                             "  [{}/{}] {}",
+                             crawler.done(),
+                             crawler.total(),
+                             series_songs.series.title)
+                        .chain_err(|| "failed to write to stderr")?;
+                    snapshot.add_series(category_id, series_songs);
+                }
+            }
+            None => {
+                let mut request = client.songs().by_category_id(category_id);
+                if serial_no.is_some() {
+                    request.set_serial_no(serial_no);
+                }
+
+                snapshot.add_songs(category_id, request.send_all()?.items);
+            }
+        }
+    }
+
+    Ok(snapshot)
+}
@@ -1,10 +1,20 @@
 use app;
 use app::AppExt;
-use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
 use clubdarn;
+use clubdarn::catalogue;
+use config::Config;
 use error::*;
 #[cfg(feature = "library")]
-use id3;
+use library::{Glob, Scanner, Skipped};
+use playlist::{self, Entry};
+#[cfg(feature = "library")]
+use report::Report;
+use serde_json;
+use std::io::{self, BufRead, Write};
+use table::Tabular;
+#[cfg(feature = "library")]
+use tags;
 
 pub fn app() -> App<'static, 'static> {
     let title = SubCommand::with_name("title")
@@ -13,11 +23,60 @@ pub fn app() -> App<'static, 'static> {
             .help("Require that the match occurs at the beginning of the song title")
             .long("starts-with"))
         .arg(Arg::with_name("query")
-            .help("The query to match on")
+            .help("The query to match on, or - to read queries from stdin")
             .value_name("QUERY")
             .multiple(true)
             .empty_values(false)
+            .required_unless("stdin"))
+        .arg(stdin_arg("Read queries from stdin, one per line, printing a JSON line for each"))
+        .with_song_filter_args()
+        .with_global_args();
+
+    let search = SubCommand::with_name("search")
+        .about("Find songs matching a combination of title, artist, series and category")
+        .arg(Arg::with_name("title")
+            .help("Song title")
+            .long("title")
+            .short("t")
+            .value_name("SONG_TITLE")
+            .empty_values(false)
+            .takes_value(true))
+        .arg(Arg::with_name("title-starts-with")
+            .help("Require that the match occurs at the beginning of the song title")
+            .long("title-starts-with")
+            .requires("title"))
+        .arg(Arg::with_name("artist-name")
+            .help("Artist name")
+            .long("artist")
+            .short("a")
+            .value_name("ARTIST_NAME")
+            .empty_values(false)
+            .takes_value(true))
+        .arg(Arg::with_name("artist-starts-with")
+            .help("Require that the match occurs at the beginning of the artist name")
+            .long("artist-starts-with")
+            .requires("artist-name"))
+        .arg(Arg::with_name("artist-id")
+            .help("Artist ID")
+            .long("artist-id")
+            .value_name("ARTIST_ID")
+            .takes_value(true))
+        .arg(Arg::with_name("series-title")
+            .help("The series title")
+            .long("series")
+            .value_name("SERIES_TITLE")
+            .empty_values(false)
+            .takes_value(true))
+        .arg(Arg::with_name("category-id")
+            .help("Category ID")
+            .long("category-id")
+            .value_name("CATEGORY_ID")
+            .takes_value(true))
+        .group(ArgGroup::with_name("criteria")
+            .args(&["title", "artist-name", "artist-id", "series-title", "category-id"])
+            .multiple(true)
             .required(true))
+        .with_song_filter_args()
         .with_global_args();
 
     let artist = SubCommand::with_name("artist")
@@ -32,8 +91,18 @@ pub fn app() -> App<'static, 'static> {
         .arg(Arg::with_name("live")
             .help("List artist's live performances")
             .long("live"))
+        .with_song_filter_args()
         .with_global_args();
 
+    let by_artist_name = SubCommand::with_name("by-artist-name")
+        .about("Find songs by an artist's name")
+        .arg(Arg::with_name("artist-name")
+            .help("The artist's name. If it matches multiple artists, they are listed instead.")
+            .value_name("ARTIST_NAME")
+            .multiple(true)
+            .empty_values(false)
+            .required(true))
+        .with_global_args();
 
     // TODO: Put in a common place (also used by root series subcommand)
     let series_categories = (&clubdarn::category::series::CATEGORIES)
@@ -54,6 +123,7 @@ pub fn app() -> App<'static, 'static> {
             .value_name("CATEGORY_ID")
             .required(true)
             .possible_values(&series_categories))
+        .with_song_filter_args()
         .with_global_args();
 
     let category = SubCommand::with_name("category")
@@ -62,23 +132,34 @@ pub fn app() -> App<'static, 'static> {
             .help("Category ID")
             .value_name("CATEGORY_ID")
             .required(true))
+        .with_song_filter_args()
         .with_global_args();
 
     let id = SubCommand::with_name("id")
         .about("Find songs with ID")
         .arg(Arg::with_name("song-id")
-            .help("ID without hyphen (e.g., 360715)")
+            .help("ID without hyphen (e.g., 360715), or - to read IDs from stdin")
             .value_name("SONG_ID")
             .multiple(true)
-            .required(true))
+            .required_unless("stdin"))
+        .arg(stdin_arg("Read IDs from stdin, one per line, printing a JSON line for each"))
+        .with_song_filter_args()
         .with_global_args();
 
     let similar = SubCommand::with_name("similar")
-        .about("Find songs similar to the given song ID")
+        .about("Find songs similar to the given song IDs, ranked by how many of them they match")
         .arg(Arg::with_name("song-id")
             .help("ID without hyphen (e.g., 360715)")
             .value_name("SONG_ID")
+            .multiple(true)
             .required(true))
+        .arg(Arg::with_name("enrich")
+            .help("Look up missing details (e.g., video, date added) for each song")
+            .long("enrich"))
+        .arg(Arg::with_name("raw")
+            .help("Include fields specific to the recommend API, without ranking")
+            .long("raw"))
+        .with_song_filter_args()
         .with_global_args();
 
     let exact = SubCommand::with_name("exact")
@@ -90,7 +171,7 @@ pub fn app() -> App<'static, 'static> {
             .value_name("SONG_TITLE")
             .multiple(true)
             .number_of_values(1)
-            .required(true))
+            .required_unless_one(&["stdin", "input"]))
         .arg(Arg::with_name("artist-name")
             .help("Artist name")
             .long("artist")
@@ -98,29 +179,78 @@ pub fn app() -> App<'static, 'static> {
             .value_name("ARTIST_NAME")
             .multiple(true)
             .number_of_values(1)
+            .required_unless_one(&["stdin", "input"]))
+        .arg(Arg::with_name("input")
+            .help("- to read titles and artists from stdin, as with --stdin")
+            .value_name("INPUT")
+            .possible_value("-")
+            .conflicts_with_all(&["song-title", "artist-name"]))
+        .arg(stdin_arg("Read tab-separated titles and artists from stdin, one pair per line, \
+                       printing a JSON line for each")
+            .conflicts_with_all(&["song-title", "artist-name"]))
+        .with_song_filter_args()
+        .with_global_args();
+
+    let playlist = SubCommand::with_name("playlist")
+        .about("Check which entries in a playlist (M3U, PLS or CSV with title and artist \
+                columns) are available")
+        .arg(Arg::with_name("file-path")
+            .help("Path to playlist, which should be UTF-8")
+            .value_name("FILE_PATH")
             .required(true))
+        .arg(Arg::with_name("type")
+            .help("Playlist type, if it can't be told from the file extension")
+            .long("type")
+            .value_name("TYPE")
+            .possible_values(&playlist::KINDS))
         .with_global_args();
 
     #[cfg(feature = "library")]
     let library = SubCommand::with_name("library")
-        .about("Find songs based on local file metadata")
+        .about("Find songs based on local file metadata (ID3, FLAC, Ogg Vorbis, Opus or MP4)")
         .arg(Arg::with_name("file-path")
-            .help("Path to file")
+            .help("Path to file, or to a directory to scan recursively")
             .value_name("FILE_PATH")
             .multiple(true)
             .required(true))
+        .arg(Arg::with_name("include")
+            .help("Only scan files matching this glob (e.g., '*.flac' or 'Anime/**/*.m4a'). \
+                   By default, files with known audio extensions are scanned.")
+            .long("include")
+            .value_name("GLOB")
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("exclude")
+            .help("Skip files and directories matching this glob")
+            .long("exclude")
+            .value_name("GLOB")
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("report")
+            .help("Write each file's tags, matched song, match method (exact, normalized or \
+                   fuzzy) and confidence to a CSV file, instead of listing the songs")
+            .long("report")
+            .value_name("FILE"))
+        .arg(Arg::with_name("resume")
+            .help("Skip files that were already matched in the --report file")
+            .long("resume")
+            .requires("report"))
+        .with_song_filter_args()
         .with_global_args();
 
     let sub = SubCommand::with_name("song")
         .about("Find songs")
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .subcommand(title)
+        .subcommand(search)
         .subcommand(artist)
+        .subcommand(by_artist_name)
         .subcommand(series)
         .subcommand(category)
         .subcommand(id)
         .subcommand(similar)
-        .subcommand(exact);
+        .subcommand(exact)
+        .subcommand(playlist);
 
     #[cfg(feature = "library")]
     {
@@ -137,56 +267,295 @@ fn collect_query(matches: &ArgMatches, arg_name: &str) -> String {
     matches.values_of(arg_name).unwrap().collect::<Vec<_>>().join(" ")
 }
 
-pub fn run(matches: &ArgMatches) -> Result<()> {
-    let context = app::Context::from_matches(matches)?;
+fn stdin_arg(help: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name("stdin")
+        .help(help)
+        .long("stdin")
+}
+
+// Either `--stdin`, or `-` in place of the arguments
+fn reads_stdin(matches: &ArgMatches, arg_name: &str) -> bool {
+    matches.is_present("stdin") ||
+    matches.values_of(arg_name).map_or(false, |values| values.collect::<Vec<_>>() == ["-"])
+}
+
+#[derive(Serialize)]
+struct LineResult {
+    line: usize,
+    input: String,
+    matches: Vec<clubdarn::Song>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// Looks up each line of stdin separately, printing each result as a JSON line as soon
+// as it's found. Errors are reported for the line that caused them (on stderr, as well
+// as in the output) without stopping.
+fn run_batch<F>(mut lookup: F) -> Result<()>
+    where F: FnMut(&str) -> Result<clubdarn::Paginated<clubdarn::Song>>
+{
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for (index, line) in stdin.lock().lines().enumerate() {
+        let (input, found) = match line {
+            Ok(line) => {
+                let input = line.trim().to_string();
+                if input.is_empty() {
+                    continue;
+                }
+                let found = lookup(&input);
+                (input, found)
+            }
+            Err(e) => {
+                // The rest of the input can still be read if this line wasn't valid UTF-8
+                let is_invalid_line = e.kind() == io::ErrorKind::InvalidData;
+                let error = Error::with_chain(e, "failed to read from stdin");
+                if !is_invalid_line {
+                    return Err(error);
+                }
+                (String::new(), Err(error))
+            }
+        };
+
+        let result = match found {
+            Ok(page) => {
+                LineResult {
+                    line: index + 1,
+                    input: input,
+                    matches: page.items,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                writeln!(&mut io::stderr(), "line {}: {}", index + 1, e)
+                    .chain_err(|| "failed to write to stderr")?;
+
+                LineResult {
+                    line: index + 1,
+                    input: input,
+                    matches: Vec::new(),
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+
+        let json = serde_json::to_string(&result).chain_err(|| "failed to serialize JSON")?;
+        writeln!(stdout, "{}", json).chain_err(|| "failed to write to stdout")?;
+    }
+
+    Ok(())
+}
+
+// Lists files that couldn't be read on stderr, followed by the number of each reason
+#[cfg(feature = "library")]
+fn report_skipped(skipped: &[Skipped]) -> Result<()> {
+    use std::collections::BTreeMap;
+
+    let stderr = &mut io::stderr();
+    let mut reasons = BTreeMap::new();
+
+    for file in skipped {
+        writeln!(stderr, "skipped {}: {}", file.path.display(), file.reason)
+            .chain_err(|| "failed to write to stderr")?;
+        *reasons.entry(file.reason.as_str()).or_insert(0) += 1;
+    }
+
+    if !skipped.is_empty() {
+        writeln!(stderr, "skipped {} files:", skipped.len())
+            .chain_err(|| "failed to write to stderr")?;
+    }
+    for (reason, count) in reasons {
+        writeln!(stderr, "  {} {}", count, reason).chain_err(|| "failed to write to stderr")?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct PlaylistResult<'e> {
+    entry: &'e Entry,
+    // `None` if the entry isn't available, or has no title or artist
+    song: Option<clubdarn::Song>,
+}
+
+impl<'e> Tabular for PlaylistResult<'e> {}
+
+fn match_type(matches: &ArgMatches, starts_with_arg: &str) -> clubdarn::MatchType {
+    if matches.is_present(starts_with_arg) {
+        clubdarn::MatchType::StartsWith
+    } else {
+        clubdarn::MatchType::Contains
+    }
+}
+
+pub fn run(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let context = app::Context::from_matches(matches, config)?;
+    let filter_args = app::SongFilterArgs::from_matches(matches)?;
     let songs = context.client.songs();
 
-    let query: String;
+    match matches.subcommand_name() {
+        Some("title") | Some("artist") | Some("series") | Some("category") => {}
+        Some(other) => context.require_online(&format!("song {}", other))?,
+        None => {}
+    }
 
     let mut request = match matches.subcommand() {
+        // These can be answered offline, so they go through the catalogue
         ("title", Some(matches)) => {
-            let match_type = if matches.is_present("starts-with") {
-                clubdarn::MatchType::StartsWith
-            } else {
-                clubdarn::MatchType::Contains
-            };
+            let title_match_type = match_type(matches, "starts-with");
+
+            if reads_stdin(matches, "query") {
+                return run_batch(|title| {
+                    let pages = catalogue::pages(context.page, |page| {
+                        context.catalogue().songs_by_title(title, title_match_type, page)
+                    });
+                    filter_args.fetch(&context, pages)
+                });
+            }
 
-            query = collect_query(matches, "query");
+            let query = collect_query(matches, "query");
 
-            songs.by_title(&query, match_type)
+            let pages = catalogue::pages(context.page, |page| {
+                context.catalogue().songs_by_title(&query, title_match_type, page)
+            });
+            return filter_args.print(&context, pages);
+        }
+        ("search", Some(matches)) => {
+            let mut song_query = clubdarn::SongQuery::new();
+
+            if let Some(title) = matches.value_of("title") {
+                song_query = song_query.title(title, match_type(matches, "title-starts-with"));
+            }
+            if let Some(name) = matches.value_of("artist-name") {
+                let artist_match_type = match_type(matches, "artist-starts-with");
+                song_query = song_query.artist_name(name, artist_match_type);
+            }
+            if matches.is_present("artist-id") {
+                song_query = song_query.artist_id(value_t!(matches, "artist-id", u32)?);
+            }
+            if let Some(series) = matches.value_of("series-title") {
+                song_query = song_query.series(series);
+            }
+            if let Some(category_id) = matches.value_of("category-id") {
+                song_query = song_query.category_id(category_id);
+            }
+
+            songs.by_query(song_query)
         }
         ("artist", Some(matches)) => {
             let artist_id = value_t!(matches, "artist-id", u32)?;
 
-            if matches.is_present("live") {
-                songs.by_artist_in_category_id(artist_id, clubdarn::category::LIVE_PERFORMANCE.id.0)
+            let category_id = if matches.is_present("live") {
+                clubdarn::category::LIVE_PERFORMANCE.id.0
             } else {
-                songs.by_artist_id(artist_id)
+                clubdarn::category::ARTIST_NAME.id.0
+            };
+
+            let pages = catalogue::pages(context.page, |page| {
+                context.catalogue().songs_by_artist_in_category_id(artist_id, category_id, page)
+            });
+            return filter_args.print(&context, pages);
+        }
+        ("by-artist-name", Some(matches)) => {
+            use clubdarn::artist::ArtistMatch;
+
+            let name = collect_query(matches, "artist-name");
+
+            match context.client.songs_by_artist_name(&name, context.page, None)? {
+                ArtistMatch::Found(result) => return context.printer.stdout(&result),
+                ArtistMatch::Ambiguous(candidates) => {
+                    let names = candidates.iter()
+                        .map(|artist| format!("{} ({})", artist.name, artist.id.0))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    Err(format!("no single artist matches {} exactly: {}", name, names))?
+                }
+                ArtistMatch::NotFound => Err(format!("no artist found matching {}", name))?,
             }
         }
         ("series", Some(matches)) => {
-            query = collect_query(matches, "series-title");
+            let query = collect_query(matches, "series-title");
             let category_id = matches.value_of("category-id").unwrap();
-            songs.by_series_in_category_id(&query, category_id)
+
+            let pages = catalogue::pages(context.page, |page| {
+                context.catalogue().songs_by_series_in_category_id(&query, category_id, page)
+            });
+            return filter_args.print(&context, pages);
         }
         ("category", Some(matches)) => {
             let category_id = matches.value_of("category-id").unwrap();
-            songs.by_category_id(category_id)
+
+            let pages = catalogue::pages(context.page, |page| {
+                context.catalogue().songs_by_category_id(category_id, page)
+            });
+            return filter_args.print(&context, pages);
         }
         // Looking up a song by ID uses a different request type,
         // which would cause these match arms to have a incompatible
         // types. We use an explicit returns here to avoid that.
         ("id", Some(matches)) => {
+            if reads_stdin(matches, "song-id") {
+                return run_batch(|input| {
+                    // Also accepts IDs as shown in Denmoku, e.g., 3607-15
+                    let id = input.replace('-', "")
+                        .parse::<u32>()
+                        .chain_err(|| format!("invalid song ID {}", input))?;
+                    let ids = [id];
+                    let mut request = context.client.songs().by_ids(&ids);
+                    filter_args.fetch(&context, request.set_page(context.page).pages())
+                });
+            }
+
             let ids = values_t!(matches, "song-id", u32)?;
-            let result = songs.by_ids(&ids).set_page(context.page).send()?;
-            return context.printer.stdout(&result);
+            let mut request = songs.by_ids(&ids);
+            return filter_args.print(&context, request.set_page(context.page).pages());
         }
         ("similar", Some(matches)) => {
-            let id = value_t!(matches, "song-id", u32)?;
-            let result = songs.similar_to(id).set_page(context.page).send()?;
+            let ids = values_t!(matches, "song-id", u32)?;
+
+            let enrich = matches.is_present("enrich") || filter_args.needs_enrichment();
+
+            if matches.is_present("raw") {
+                let mut result = context.client.recommendations().similar_to_any(&ids).send()?;
+                if enrich {
+                    context.client.enrich(result.items.iter_mut().map(|r| &mut r.song), None)?;
+                }
+                filter_args.apply_recommended(&mut result);
+                return context.printer.stdout(&result);
+            }
+
+            let mut result = if ids.len() == 1 {
+                songs.similar_to(ids[0]).send()?
+            } else {
+                context.client.similar_to_all(&ids, None)?
+            };
+
+            if enrich {
+                context.client.enrich(result.items.iter_mut(), None)?;
+            }
+
+            filter_args.apply(&mut result);
             return context.printer.stdout(&result);
         }
         ("exact", Some(matches)) => {
+            if reads_stdin(matches, "input") {
+                return run_batch(|input| {
+                    let (title, artist) = match input.find('\t') {
+                        Some(index) => (input[..index].trim(), input[index + 1..].trim()),
+                        None => Err("expected a title and artist separated by a tab")?,
+                    };
+                    let titles_and_artists = [clubdarn::TitleAndArtist {
+                                                  title: title.into(),
+                                                  artist: artist.into(),
+                                              }];
+                    let mut request =
+                        context.client.songs().by_titles_and_artists(&titles_and_artists);
+                    filter_args.fetch(&context, request.set_page(context.page).pages())
+                });
+            }
+
             let titles = matches.values_of("song-title").unwrap();
             let artists = matches.values_of("artist-name").unwrap();
 
@@ -199,39 +568,99 @@ pub fn run(matches: &ArgMatches) -> Result<()> {
                 })
                 .collect::<Vec<_>>();
 
-            let result = songs.by_titles_and_artists(&zipped).set_page(context.page).send()?;
-            return context.printer.stdout(&result);
+            let mut request = songs.by_titles_and_artists(&zipped);
+            return filter_args.print(&context, request.set_page(context.page).pages());
+        }
+        ("playlist", Some(matches)) => {
+            let path = matches.value_of("file-path").unwrap();
+            let kind = match matches.value_of("type") {
+                Some(kind) => kind.parse()?,
+                None => playlist::Kind::from_path(path)?,
+            };
+
+            let entries = playlist::read(path, kind)?;
+            let queries = entries.iter().filter_map(Entry::title_and_artist).collect::<Vec<_>>();
+            let mut songs = context.client.find_titles_and_artists(&queries, None)?.into_iter();
+
+            let results = entries.iter()
+                .map(|entry| {
+                    PlaylistResult {
+                        entry: entry,
+                        song: match entry.title_and_artist() {
+                            Some(_) => songs.next().and_then(|song| song),
+                            None => None,
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let found = results.iter().filter(|result| result.song.is_some()).count();
+            writeln!(&mut io::stderr(),
+                     "{} of {} entries are available",
+                     found,
+                     results.len())
+                .chain_err(|| "failed to write to stderr")?;
+
+            return context.printer.stdout(&results);
         }
         #[cfg(feature = "library")]
         ("library", Some(matches)) => {
-            let paths = matches.values_of("file-path").unwrap();
-
-            let meta = paths.flat_map(|p| {
-                    id3::Tag::read_from_path(p)
-                        .ok()
-                        .and_then(|tag| if let (Some(title), Some(artist)) =
-                            (tag.title(), tag.artist()) {
-                            Some(clubdarn::TitleAndArtist {
-                                title: title.to_string().into(),
-                                artist: artist.to_string().into(),
-                            })
-                        } else {
-                            None
+            let globs = |name: &str| {
+                matches.values_of(name).map_or(Vec::new(), |globs| globs.map(Glob::new).collect())
+            };
+            let scanner = Scanner {
+                include: globs("include"),
+                exclude: globs("exclude"),
+            };
+
+            let report = match matches.value_of("report") {
+                Some(path) => Some(Report::create(path, matches.is_present("resume"))?),
+                None => None,
+            };
+
+            let scan = scanner.scan(matches.values_of("file-path").unwrap());
+            let mut skipped = scan.skipped;
+            let mut files = Vec::new();
+            let mut meta = Vec::new();
+
+            for path in scan.files {
+                if report.as_ref().map_or(false, |report| report.is_matched(&path)) {
+                    continue;
+                }
+
+                match tags::read(&path).and_then(|tags| tags.title_and_artist()) {
+                    Ok((title, artist)) => {
+                        files.push(path);
+                        meta.push(clubdarn::TitleAndArtist {
+                            title: title.into(),
+                            artist: artist.into(),
                         })
-                })
-                .collect::<Vec<_>>();
+                    }
+                    Err(e) => {
+                        let causes = e.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                        skipped.push(Skipped {
+                            path: path,
+                            reason: causes.join(": "),
+                        })
+                    }
+                }
+            }
+
+            report_skipped(&skipped)?;
+
+            if let Some(mut report) = report {
+                return report.add_files(&context.client, &files, &meta, &skipped);
+            }
 
             if meta.is_empty() {
                 Err("no song metadata found")?
             }
 
-            let result = songs.by_titles_and_artists(&meta).set_page(context.page).send()?;
-            return context.printer.stdout(&result);
+            let mut request = songs.by_titles_and_artists(&meta);
+            return filter_args.print(&context, request.set_page(context.page).pages());
         }
         (other, _) => Err(format!("unrecognized subcommand {}", other))?,
     };
 
-    let result = request.set_page(context.page).send()?;
-
-    context.printer.stdout(&result)
+    filter_args.print(&context, request.set_page(context.page).pages())
 }
@@ -0,0 +1,293 @@
+use clubdarn::{Artist, Paginated, RecommendedSong, Series, Song, SongId};
+use clubdarn::artist::{ArtistSongs, DiscographySong};
+use clubdarn::category::{Category, CategoryType};
+use clubdarn::compare::{Availability, SongAvailability};
+use clubdarn::diff::Diff;
+use clubdarn::ranking::Movements;
+use clubdarn::series::SeriesSongs;
+use flatten::{self, Row};
+use serde_json::Value;
+use unicode_width::UnicodeWidthStr;
+
+pub struct Layout {
+    headers: &'static [&'static str],
+    row: fn(&Value) -> Vec<String>,
+}
+
+static SONGS: Layout = Layout {
+    headers: &["ID", "TITLE", "ARTIST", "SERIES", "VIDEO", "ADDED"],
+    row: song_row,
+};
+
+static ARTISTS: Layout = Layout {
+    headers: &["ID", "NAME", "KANA"],
+    row: artist_row,
+};
+
+static SERIES: Layout = Layout {
+    headers: &["TITLE", "KANA"],
+    row: series_row,
+};
+
+static CATEGORIES: Layout = Layout {
+    headers: &["ID", "NAME", "NAME (JA)"],
+    row: category_row,
+};
+
+fn field<'v>(value: &'v Value, path: &[&str]) -> Option<&'v Value> {
+    path.iter().fold(Some(value), |value, key| value.and_then(|v| v.get(*key)))
+}
+
+fn text(value: &Value, path: &[&str]) -> String {
+    match field(value, path) {
+        Some(&Value::String(ref s)) => s.clone(),
+        Some(&Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn song_id(value: &Value) -> String {
+    match field(value, &["id"]).and_then(Value::as_u64) {
+        Some(id) => SongId(id as u32).to_string(),
+        None => String::new(),
+    }
+}
+
+fn song_row(item: &Value) -> Vec<String> {
+    let song = flatten::song(item);
+    let has_video = field(song, &["hasVideo"]).and_then(Value::as_bool).unwrap_or(false);
+
+    vec![song_id(song),
+         text(song, &["title"]),
+         text(song, &["artist", "name"]),
+         text(song, &["series"]),
+         if has_video { "yes" } else { "" }.to_string(),
+         text(song, &["dateAdded"])]
+}
+
+fn artist_row(item: &Value) -> Vec<String> {
+    vec![text(item, &["id"]), text(item, &["name"]), text(item, &["firstKana"])]
+}
+
+fn series_row(item: &Value) -> Vec<String> {
+    vec![text(item, &["title"]), text(item, &["firstKana"])]
+}
+
+fn category_row(item: &Value) -> Vec<String> {
+    vec![text(item, &["id"]),
+         text(item, &["description", "en"]),
+         text(item, &["description", "ja"])]
+}
+
+// Types that `Printer` can output, along with the layout of their rows when shown
+// as a table. Types without a layout are shown as JSON instead.
+pub trait Tabular {
+    fn layout() -> Option<&'static Layout> {
+        None
+    }
+}
+
+impl<'a, T: Tabular + ?Sized> Tabular for &'a T {
+    fn layout() -> Option<&'static Layout> {
+        T::layout()
+    }
+}
+
+impl<T: Tabular> Tabular for [T] {
+    fn layout() -> Option<&'static Layout> {
+        T::layout()
+    }
+}
+
+impl<T: Tabular> Tabular for Vec<T> {
+    fn layout() -> Option<&'static Layout> {
+        T::layout()
+    }
+}
+
+impl<T: Tabular> Tabular for Paginated<T> {
+    fn layout() -> Option<&'static Layout> {
+        T::layout()
+    }
+}
+
+macro_rules! tabular {
+    ($layout:ident: $($t:ty),+) => {
+        $(
+            impl Tabular for $t {
+                fn layout() -> Option<&'static Layout> {
+                    Some(&$layout)
+                }
+            }
+        )+
+    };
+}
+
+tabular!(SONGS: Song, DiscographySong, RecommendedSong, SongAvailability, ArtistSongs);
+tabular!(ARTISTS: Artist);
+tabular!(SERIES: Series);
+
+impl<T: CategoryType> Tabular for Category<T> {
+    fn layout() -> Option<&'static Layout> {
+        Some(&CATEGORIES)
+    }
+}
+
+impl Tabular for Availability {}
+impl Tabular for Diff {}
+impl Tabular for Movements {}
+impl Tabular for SeriesSongs {}
+
+// Pads each column to the width of its widest cell, counting wide characters
+// (e.g., kanji) as two columns
+fn align(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths = (0..columns)
+        .map(|i| rows.iter().filter_map(|row| row.get(i)).map(|cell| cell.width()).max())
+        .map(|width| width.unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    rows.iter()
+        .map(|row| {
+            let mut line = String::new();
+            for (i, cell) in row.iter().enumerate() {
+                line.push_str(cell);
+                if i + 1 < row.len() {
+                    let padding = widths[i] - cell.width() + 2;
+                    line.extend(::std::iter::repeat(' ').take(padding));
+                }
+            }
+            line.trim_right().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_items(items: &[Value], layout: &Layout, numbered: bool) -> String {
+    if items.is_empty() {
+        return "No results".to_string();
+    }
+
+    let mut rows = vec![layout.headers.iter().map(|h| h.to_string()).collect::<Vec<_>>()];
+    rows.extend(items.iter().map(|item| (layout.row)(item)));
+
+    if numbered {
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.insert(0, if i == 0 { "#".to_string() } else { i.to_string() });
+        }
+    }
+
+    align(&rows)
+}
+
+fn render_page(page: &Value, layout: &Layout, numbered: bool) -> Option<String> {
+    let table = field(page, &["items"])
+        .and_then(Value::as_array)
+        .map(|items| render_items(items, layout, numbered));
+
+    table.map(|table| {
+        format!("{}\n\nPage {} of {} ({} items)",
+                table,
+                text(page, &["page"]),
+                text(page, &["totalPages"]),
+                text(page, &["totalItems"]))
+    })
+}
+
+// Renders a listing of `T` (e.g., a list or page of songs) as aligned columns.
+// Returns `None` for anything else, which should then be printed as JSON.
+pub fn render<T: Tabular + ?Sized>(value: &Value) -> Option<String> {
+    let layout = match T::layout() {
+        Some(layout) => layout,
+        None => return None,
+    };
+
+    if let Some(items) = value.as_array() {
+        return Some(render_items(items, layout, false));
+    }
+
+    if field(value, &["items"]).is_some() {
+        return render_page(value, layout, false);
+    }
+
+    // An artist along with a page of their songs
+    if let (Some(artist), Some(songs)) = (field(value, &["artist"]), field(value, &["songs"])) {
+        let header = format!("{} ({})", text(artist, &["name"]), text(artist, &["id"]));
+        return render_page(songs, layout, false)
+            .map(|table| format!("{}\n\n{}", header, table));
+    }
+
+    None
+}
+
+// Renders a listing or a page of results with each item numbered from 1
+pub fn render_numbered<T: Tabular + ?Sized>(value: &Value) -> Option<String> {
+    let layout = match T::layout() {
+        Some(layout) => layout,
+        None => return None,
+    };
+
+    match value.as_array() {
+        Some(items) => Some(render_items(items, layout, true)),
+        None => render_page(value, layout, true),
+    }
+}
+
+// Renders rows of selected fields, using the field names as headers
+pub fn render_rows(rows: &[Row]) -> String {
+    let headers = flatten::headers(rows);
+
+    let mut cells = vec![headers.iter().map(|h| h.to_string()).collect::<Vec<_>>()];
+    cells.extend(rows.iter().map(|row| {
+        headers.iter()
+            .map(|header| {
+                row.iter()
+                    .find(|&&(ref key, _)| key == header)
+                    .map_or(String::new(), |&(_, ref value)| flatten::text(value))
+            })
+            .collect()
+    }));
+
+    align(&cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn cells(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn columns_padded_to_widest_cell() {
+        let rows = cells(&[&["ID", "TITLE"], &["1", "Title"], &["100", "T"]]);
+        assert_eq!(align(&rows), "ID   TITLE\n1    Title\n100  T");
+    }
+
+    #[test]
+    fn wide_characters_count_as_two_columns() {
+        let rows = cells(&[&["曲名", "X"], &["abc", "Y"], &["ａ", "Z"]]);
+        assert_eq!(align(&rows), "曲名  X\nabc   Y\nａ    Z");
+    }
+
+    #[test]
+    fn layout_from_type() {
+        let page = serde_json::from_str::<Value>(r#"{
+            "page": 1, "totalPages": 1, "totalItems": 1,
+            "items": [{"id": 360715, "title": "Title", "artist": {"name": "Artist"}}]
+        }"#)
+            .unwrap();
+
+        let table = render::<Paginated<Song>>(&page).unwrap();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "ID       TITLE  ARTIST  SERIES  VIDEO  ADDED");
+        assert_eq!(lines[1], "3607-15  Title  Artist");
+        assert_eq!(lines[3], "Page 1 of 1 (1 items)");
+
+        assert!(render::<Diff>(&page).is_none());
+        assert_eq!(render::<Vec<Artist>>(&serde_json::from_str("[]").unwrap()),
+                   Some("No results".to_string()));
+    }
+}
@@ -0,0 +1,461 @@
+// Reads titles and artists from audio files. ID3 tags are read with the `id3` crate,
+// while Vorbis comments (FLAC, Ogg Vorbis and Opus) and MP4 metadata are read here.
+
+use error::*;
+use id3;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::mem;
+use std::path::Path;
+
+// Extensions of files that are read when scanning directories, unless `--include` is given
+pub const EXTENSIONS: [&'static str; 7] = ["mp3", "flac", "ogg", "oga", "opus", "m4a", "mp4"];
+
+// Metadata (e.g., a `moov` atom) larger than this is treated as corrupt rather than read
+// into memory, since sizes come from the file itself
+const MAX_READ_LEN: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct Tags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+}
+
+impl Tags {
+    // Falls back to the album artist if there's no artist
+    pub fn title_and_artist(self) -> Result<(String, String)> {
+        match (self.title, self.artist.or(self.album_artist)) {
+            (Some(title), Some(artist)) => Ok((title, artist)),
+            (None, Some(_)) => Err("no title tag".into()),
+            (Some(_), None) => Err("no artist tag".into()),
+            (None, None) => Err("no title or artist tags".into()),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: String) {
+        let field = match key.to_uppercase().as_str() {
+            "TITLE" | "\u{a9}NAM" => &mut self.title,
+            "ARTIST" | "\u{a9}ART" => &mut self.artist,
+            "ALBUMARTIST" | "ALBUM ARTIST" | "AART" => &mut self.album_artist,
+            _ => return,
+        };
+
+        // Only the first value is kept if a tag is repeated
+        if field.is_none() && !value.trim().is_empty() {
+            *field = Some(value.trim().to_string());
+        }
+    }
+}
+
+pub fn read(path: &Path) -> Result<Tags> {
+    let file = File::open(path).chain_err(|| "failed to open file")?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic).chain_err(|| "not an audio file")?;
+    reader.seek(SeekFrom::Start(0)).chain_err(|| "failed to read file")?;
+
+    let is_mp3 = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ext.to_lowercase() == "mp3");
+
+    if magic.starts_with(b"fLaC") {
+        read_flac(&mut reader).chain_err(|| "invalid FLAC metadata")
+    } else if magic.starts_with(b"OggS") {
+        read_ogg(&mut reader).chain_err(|| "invalid Ogg metadata")
+    } else if &magic[4..] == b"ftyp" {
+        read_mp4(&mut reader).chain_err(|| "invalid MP4 metadata")
+    } else if magic.starts_with(b"ID3") || is_mp3 {
+        let tag = id3::Tag::read_from_path(path)
+            .map_err(|e| format!("invalid ID3 tag: {}", e))?;
+
+        Ok(Tags {
+            title: tag.title().map(|title| title.to_string()),
+            artist: tag.artist().map(|artist| artist.to_string()),
+            album_artist: None,
+        })
+    } else {
+        Err("unsupported file type".into())
+    }
+}
+
+// Reads fields from the front of a slice
+struct Bytes<'b>(&'b [u8]);
+
+impl<'b> Bytes<'b> {
+    fn take(&mut self, len: usize) -> Result<&'b [u8]> {
+        if self.0.len() < len {
+            Err("unexpected end of data")?
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32_le(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok((b[0] as u32) | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+    }
+}
+
+fn u32_be(b: &[u8]) -> u32 {
+    (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | (b[3] as u32)
+}
+
+// Reads as the data arrives rather than allocating `len` bytes up front, so a bad
+// length in a truncated file fails at the end of the file
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    if len > MAX_READ_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "too large"));
+    }
+
+    let mut bytes = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() < len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of file"));
+    }
+
+    Ok(bytes)
+}
+
+// A vendor string followed by `KEY=value` comments, each prefixed by its length
+fn read_vorbis_comments(data: &[u8]) -> Result<Tags> {
+    let mut bytes = Bytes(data);
+    let vendor_len = bytes.u32_le()? as usize;
+    bytes.take(vendor_len)?;
+
+    let mut tags = Tags::default();
+
+    for _ in 0..bytes.u32_le()? {
+        let len = bytes.u32_le()? as usize;
+        let comment = String::from_utf8_lossy(bytes.take(len)?);
+
+        if let Some(index) = comment.find('=') {
+            tags.set(&comment[..index], comment[index + 1..].to_string());
+        }
+    }
+
+    Ok(tags)
+}
+
+// After the `fLaC` marker, metadata blocks have a 4-byte header: the high bit marks the
+// last block, followed by the block type (4 for Vorbis comments) and a 24-bit length
+fn read_flac<R: Read + Seek>(reader: &mut R) -> Result<Tags> {
+    reader.seek(SeekFrom::Start(4)).chain_err(|| "failed to read file")?;
+
+    loop {
+        let header = read_bytes(reader, 4).chain_err(|| "truncated metadata block")?;
+        let is_last = header[0] & 0x80 != 0;
+        let len = (header[1] as u32) << 16 | (header[2] as u32) << 8 | (header[3] as u32);
+
+        if header[0] & 0x7f == 4 {
+            let data = read_bytes(reader, len as usize).chain_err(|| "truncated comments")?;
+            return read_vorbis_comments(&data);
+        }
+
+        if is_last {
+            return Ok(Tags::default());
+        }
+
+        reader.seek(SeekFrom::Current(len as i64)).chain_err(|| "failed to read file")?;
+    }
+}
+
+// Reassembles the first `count` packets of the first logical stream. Each page has a
+// 27-byte header ending with the number of segments, then a table of segment lengths.
+// Packets can span pages, and end with a segment shorter than 255 bytes.
+fn read_ogg_packets<R: Read>(reader: &mut R, count: usize) -> Result<Vec<Vec<u8>>> {
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    let mut stream_serial_no = None;
+
+    while packets.len() < count {
+        let header = read_bytes(reader, 27).chain_err(|| "truncated page")?;
+        if &header[..4] != b"OggS" {
+            Err("invalid page")?
+        }
+
+        let serial_no = u32_be(&header[14..18]);
+        let segments = read_bytes(reader, header[26] as usize).chain_err(|| "truncated page")?;
+        let data_len = segments.iter().map(|&len| len as usize).sum::<usize>();
+        let data = read_bytes(reader, data_len).chain_err(|| "truncated page")?;
+
+        match stream_serial_no {
+            Some(stream_serial_no) if stream_serial_no != serial_no => continue,
+            Some(_) => {}
+            None => stream_serial_no = Some(serial_no),
+        }
+
+        let mut offset = 0;
+        for &len in &segments {
+            packet.extend_from_slice(&data[offset..offset + len as usize]);
+            offset += len as usize;
+
+            if len < 255 {
+                packets.push(mem::replace(&mut packet, Vec::new()));
+                if packets.len() == count {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(packets)
+}
+
+// Comments are in the second packet, after a codec-specific marker
+fn read_ogg<R: Read>(reader: &mut R) -> Result<Tags> {
+    let packets = read_ogg_packets(reader, 2)?;
+    let comments = &packets[1];
+
+    if comments.starts_with(b"\x03vorbis") {
+        read_vorbis_comments(&comments[7..])
+    } else if comments.starts_with(b"OpusTags") {
+        read_vorbis_comments(&comments[8..])
+    } else {
+        Err("unsupported Ogg codec".into())
+    }
+}
+
+// Atoms have a 4-byte size (including the 8-byte header) and type, or a size of 1
+// followed by a 64-bit size, or a size of 0 for an atom that runs to the end
+fn find_atom<'d>(data: &'d [u8], atom_type: &[u8]) -> Result<Option<&'d [u8]>> {
+    let mut rest = data;
+
+    while rest.len() >= 8 {
+        let (header_len, size) = match u32_be(&rest[..4]) {
+            0 => (8, rest.len()),
+            1 if rest.len() >= 16 => {
+                let size = (u32_be(&rest[8..12]) as u64) << 32 | u32_be(&rest[12..16]) as u64;
+                (16, size as usize)
+            }
+            size => (8, size as usize),
+        };
+
+        if size < header_len || size > rest.len() {
+            Err("invalid atom size")?
+        }
+
+        if &rest[4..8] == atom_type {
+            return Ok(Some(&rest[header_len..size]));
+        }
+        rest = &rest[size..];
+    }
+
+    Ok(None)
+}
+
+// Only the `moov` atom is read into memory, since the media data can be large
+fn read_mp4<R: Read + Seek>(reader: &mut R) -> Result<Tags> {
+    loop {
+        let header = read_bytes(reader, 8).chain_err(|| "no moov atom")?;
+
+        let (header_len, size) = match u32_be(&header[..4]) {
+            0 => Err("no moov atom")?,
+            1 => {
+                let size = read_bytes(reader, 8).chain_err(|| "truncated atom")?;
+                (16, (u32_be(&size[..4]) as u64) << 32 | u32_be(&size[4..]) as u64)
+            }
+            size => (8, size as u64),
+        };
+
+        if size < header_len {
+            Err("invalid atom size")?
+        }
+
+        let body_len = size - header_len;
+
+        if &header[4..] == b"moov" {
+            if body_len > MAX_READ_LEN as u64 {
+                Err("moov atom too large")?
+            }
+
+            let moov = read_bytes(reader, body_len as usize).chain_err(|| "truncated atom")?;
+            return read_mp4_metadata(&moov);
+        }
+
+        reader.seek(SeekFrom::Current(body_len as i64)).chain_err(|| "failed to read file")?;
+    }
+}
+
+// Tags are items in `moov.udta.meta.ilst` (`meta` has 4 bytes of version and flags
+// before its children), each containing a `data` atom with 8 bytes of type and locale
+// before the value
+fn read_mp4_metadata(moov: &[u8]) -> Result<Tags> {
+    let mut tags = Tags::default();
+
+    let meta = match find_atom(moov, b"udta")? {
+        Some(udta) => find_atom(udta, b"meta")?,
+        None => None,
+    };
+    let ilst = match meta {
+        Some(meta) if meta.len() >= 4 => find_atom(&meta[4..], b"ilst")?,
+        _ => None,
+    };
+    let mut items = match ilst {
+        Some(ilst) => ilst,
+        None => return Ok(tags),
+    };
+
+    while items.len() >= 8 {
+        let size = u32_be(&items[..4]) as usize;
+        if size < 8 || size > items.len() {
+            Err("invalid atom size")?
+        }
+
+        let key = items[4..8].iter().map(|&b| b as char).collect::<String>();
+        if let Some(data) = find_atom(&items[8..size], b"data")? {
+            if data.len() > 8 {
+                tags.set(&key, String::from_utf8_lossy(&data[8..]).into_owned());
+            }
+        }
+
+        items = &items[size..];
+    }
+
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn u32_le_bytes(n: u32) -> Vec<u8> {
+        vec![n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8]
+    }
+
+    fn u32_be_bytes(n: u32) -> Vec<u8> {
+        vec![(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]
+    }
+
+    fn vorbis_comments(comments: &[&str]) -> Vec<u8> {
+        let mut data = u32_le_bytes(6);
+        data.extend_from_slice(b"vendor");
+        data.extend(u32_le_bytes(comments.len() as u32));
+        for comment in comments {
+            data.extend(u32_le_bytes(comment.len() as u32));
+            data.extend_from_slice(comment.as_bytes());
+        }
+        data
+    }
+
+    fn ogg_page(serial_no: u32, segments: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS".to_vec();
+        page.extend(vec![0; 10]);
+        page.extend(u32_be_bytes(serial_no));
+        page.extend(vec![0; 8]);
+        page.push(segments.len() as u8);
+        page.extend_from_slice(segments);
+        page.extend_from_slice(data);
+        page
+    }
+
+    fn atom(atom_type: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut atom = u32_be_bytes(body.len() as u32 + 8);
+        atom.extend_from_slice(atom_type);
+        atom.extend_from_slice(body);
+        atom
+    }
+
+    fn atom64(atom_type: &[u8], size: u64, body: &[u8]) -> Vec<u8> {
+        let mut atom = u32_be_bytes(1);
+        atom.extend_from_slice(atom_type);
+        atom.extend(u32_be_bytes((size >> 32) as u32));
+        atom.extend(u32_be_bytes(size as u32));
+        atom.extend_from_slice(body);
+        atom
+    }
+
+    fn item(key: &[u8], value: &str) -> Vec<u8> {
+        let mut data = vec![0; 8];
+        data.extend_from_slice(value.as_bytes());
+        atom(key, &atom(b"data", &data))
+    }
+
+    fn mp4(moov: Vec<u8>) -> Cursor<Vec<u8>> {
+        let mut file = atom(b"ftyp", b"M4A \0\0\0\0");
+        file.extend(moov);
+        Cursor::new(file)
+    }
+
+    #[test]
+    fn flac_comments() {
+        let comments = vorbis_comments(&["TITLE=Title", "ARTIST=Artist", "ARTIST=Other"]);
+        let mut file = b"fLaC".to_vec();
+        file.extend(vec![0, 0, 0, 2, 0xaa, 0xbb]);
+        file.extend(vec![0x84, 0, 0, comments.len() as u8]);
+        file.extend(comments);
+
+        let tags = read_flac(&mut Cursor::new(file)).unwrap();
+
+        assert_eq!(tags.title, Some("Title".to_string()));
+        assert_eq!(tags.artist, Some("Artist".to_string()));
+    }
+
+    #[test]
+    fn truncated_flac_block() {
+        let mut file = b"fLaC".to_vec();
+        file.extend(vec![0x84, 0xff, 0xff, 0xff]);
+        file.extend(vorbis_comments(&["TITLE=Title"]));
+
+        assert!(read_flac(&mut Cursor::new(file)).is_err());
+    }
+
+    #[test]
+    fn ogg_packet_across_pages() {
+        let mut comments = b"\x03vorbis".to_vec();
+        comments.extend(vorbis_comments(&["TITLE=Title", "ARTIST=Artist"]));
+        comments.resize(300, 0);
+
+        let mut first = b"\x01vorbis".to_vec();
+        first.extend_from_slice(&comments[..255]);
+
+        let mut file = ogg_page(1, &[7, 255], &first);
+        // Pages of other streams are skipped
+        file.extend(ogg_page(2, &[3], b"abc"));
+        file.extend(ogg_page(1, &[45], &comments[255..]));
+
+        let tags = read_ogg(&mut Cursor::new(file)).unwrap();
+
+        assert_eq!(tags.title, Some("Title".to_string()));
+        assert_eq!(tags.artist, Some("Artist".to_string()));
+    }
+
+    #[test]
+    fn mp4_64_bit_atom_size() {
+        let ilst = [item(b"\xa9nam", "Title"), item(b"aART", "Album Artist")].concat();
+        let mut meta = vec![0; 4];
+        meta.extend(atom(b"ilst", &ilst));
+        let udta = atom(b"udta", &atom(b"meta", &meta));
+        let moov = atom64(b"moov", udta.len() as u64 + 16, &udta);
+
+        let tags = read_mp4(&mut mp4(moov)).unwrap();
+
+        assert_eq!(tags.title, Some("Title".to_string()));
+        assert_eq!(tags.album_artist, Some("Album Artist".to_string()));
+        assert_eq!(tags.title_and_artist().unwrap(),
+                   ("Title".to_string(), "Album Artist".to_string()));
+    }
+
+    #[test]
+    fn mp4_without_ilst() {
+        let mut meta = vec![0; 4];
+        meta.extend(atom(b"hdlr", b"mdirappl"));
+        let moov = atom(b"moov", &atom(b"udta", &atom(b"meta", &meta)));
+
+        let tags = read_mp4(&mut mp4(moov)).unwrap();
+
+        assert!(tags.title.is_none());
+        assert!(tags.title_and_artist().is_err());
+    }
+
+    #[test]
+    fn mp4_moov_too_large() {
+        let moov = atom64(b"moov", 1 << 40, b"");
+        assert!(read_mp4(&mut mp4(moov)).is_err());
+    }
+}
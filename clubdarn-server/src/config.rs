@@ -0,0 +1,58 @@
+use clubdarn;
+use clubdarn::machine::MachineProfiles;
+use error::*;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+
+// Environment variables used to override the recommend API parameters
+const RECOMMEND_COMP_ID: &'static str = "CLUBDARN_RECOMMEND_COMP_ID";
+const RECOMMEND_CONTRACT_ID: &'static str = "CLUBDARN_RECOMMEND_CONTRACT_ID";
+const RECOMMEND_AUTH_KEY: &'static str = "CLUBDARN_RECOMMEND_AUTH_KEY";
+const RECOMMEND_FORMAT: &'static str = "CLUBDARN_RECOMMEND_FORMAT";
+
+// Path to a TOML file of machine profiles (see `MachineProfiles::from_toml`),
+// which can be used in place of `serial_no`
+const MACHINES: &'static str = "CLUBDARN_MACHINES";
+
+// Path to a ranking history file, appended to by `clubdarn ranking record`
+const RANKING_HISTORY: &'static str = "CLUBDARN_RANKING_HISTORY";
+
+pub fn recommend_config() -> Result<clubdarn::RecommendConfig> {
+    let mut config = clubdarn::RecommendConfig::default();
+
+    if let Ok(comp_id) = env::var(RECOMMEND_COMP_ID) {
+        config.comp_id = comp_id.parse()
+            .chain_err(|| format!("invalid {} `{}`", RECOMMEND_COMP_ID, comp_id))?;
+    }
+    if let Ok(contract_id) = env::var(RECOMMEND_CONTRACT_ID) {
+        config.contract_id = contract_id.parse()
+            .chain_err(|| format!("invalid {} `{}`", RECOMMEND_CONTRACT_ID, contract_id))?;
+    }
+    if let Ok(auth_key) = env::var(RECOMMEND_AUTH_KEY) {
+        config.comp_auth_key = auth_key;
+    }
+    if let Ok(format) = env::var(RECOMMEND_FORMAT) {
+        config.format = format;
+    }
+
+    Ok(config)
+}
+
+pub fn machine_profiles() -> Result<MachineProfiles> {
+    let path = match env::var(MACHINES) {
+        Ok(path) => path,
+        Err(_) => return Ok(MachineProfiles::new()),
+    };
+
+    let mut input = String::new();
+    File::open(&path)
+        .and_then(|mut file| file.read_to_string(&mut input))
+        .chain_err(|| format!("failed to read {}", path))?;
+
+    Ok(MachineProfiles::from_toml(&input)?)
+}
+
+pub fn ranking_history_path() -> Option<String> {
+    env::var(RANKING_HISTORY).ok()
+}
@@ -1,7 +1,43 @@
 use clubdarn;
+use rocket::http::Status;
+use rocket::response::{self, Responder};
+use rocket::response::status;
+use std::io::Write;
 
 error_chain!{
     links {
         ClubDarn(clubdarn::Error, clubdarn::error::ErrorKind);
     }
+
+    errors {
+        BadRequest(message: String) {
+            description("bad request")
+            display("{}", message)
+        }
+
+        NotFound(message: String) {
+            description("not found")
+            display("{}", message)
+        }
+    }
+}
+
+// Errors caused by the request get a 4xx status along with the error message. Any other
+// error is logged and gets a 500, as Rocket does for errors that aren't responders.
+impl<'r> Responder<'r> for Error {
+    fn respond(self) -> response::Result<'r> {
+        let status = match *self.kind() {
+            ErrorKind::BadRequest(_) |
+            ErrorKind::ClubDarn(clubdarn::error::ErrorKind::TooManyPages(..)) => {
+                Status::BadRequest
+            }
+            ErrorKind::NotFound(_) => Status::NotFound,
+            _ => {
+                let _ = writeln!(&mut ::std::io::stderr(), "Response was `Err`: {:?}", self);
+                return Err(Status::InternalServerError);
+            }
+        };
+
+        status::Custom(status, self.to_string()).respond()
+    }
 }
@@ -20,11 +20,13 @@ extern crate reqwest;
 
 pub mod error;
 pub use error::*;
+mod config;
 mod responders;
 mod elastic;
 
 use responders::{Cached, Cors};
 use rocket::{Route, State};
+use rocket::request::{FormItems, FromForm, FromFormValue};
 use rocket_contrib::JSON;
 
 pub type ClientState<'a> = State<'a, clubdarn::Client<'static>>;
@@ -32,6 +34,7 @@ pub type PageResult<T> = Result<Cors<JSON<clubdarn::Paginated<T>>>>;
 pub type CachedPageResult<T> = Result<Cached<Cors<JSON<clubdarn::Paginated<T>>>>>;
 
 const CATEGORY_CACHE_TTL_SECONDS: u32 = 3600 * 6; // 6 hours
+const MAX_FILTERED_PAGES: u32 = 10;
 
 fn main() {
     let mut args = std::env::args().skip(1);
@@ -53,12 +56,27 @@ fn main() {
     let elastic_client = elastic::Client::new(elastic_url, "series".to_string())
         .expect("Failed to create client");
 
-    rocket::ignite()
+    let recommend_config = config::recommend_config().expect("Invalid recommend API config");
+    let machines = config::machine_profiles().expect("Invalid machine profiles");
+    let client = clubdarn::Client::default()
+        .expect("Failed to create ClubDAM client")
+        .set_recommend_config(recommend_config)
+        .set_machine_profiles(machines);
+
+    let mut server = rocket::ignite()
         .mount("/api/artists", artists::routes())
         .mount("/api/songs", songs::routes())
         .mount("/api/categories", categories::routes())
         .mount("/api/series", series::routes())
-        .manage(clubdarn::Client::default().unwrap())
+        .mount("/api/compare", compare::routes());
+
+    // Rankings are served from the history file, so there are no ranking routes without one
+    if let Some(path) = config::ranking_history_path() {
+        server = server.mount("/api/rankings", rankings::routes())
+            .manage(rankings::HistoryFile::new(path));
+    }
+
+    server.manage(client)
         .manage(elastic_client)
         .launch()
 }
@@ -69,17 +87,202 @@ struct CommonParams<'a> {
     serial_no: Option<&'a str>,
 }
 
+// Paging, filter and sort params shared by every route that lists songs, along with the
+// route's own params. Rocket rejects unknown params, so the shared ones are taken out here
+// and the rest are parsed as `T`.
+struct SongParams<'f, T = NoParams> {
+    page: Option<u32>,
+    serial_no: Option<&'f str>,
+    listing: ListingParams,
+    route: T,
+}
+
+#[derive(Default)]
+struct ListingParams {
+    has_video: Option<bool>,
+    has_series: Option<bool>,
+    added_after: Option<String>,
+    added_before: Option<String>,
+    first_kana: Option<String>,
+    sort: Option<String>,
+    reverse: Option<bool>,
+}
+
+// For routes that have no params besides the shared ones
+struct NoParams;
+
+impl<'f> FromForm<'f> for NoParams {
+    type Error = ();
+
+    fn from_form_items(items: &mut FormItems<'f>) -> std::result::Result<Self, ()> {
+        match items.next() {
+            Some(_) => Err(()),
+            None => Ok(NoParams),
+        }
+    }
+}
+
+impl<'f, T> FromForm<'f> for SongParams<'f, T>
+    where T: for<'x> FromForm<'x>
+{
+    type Error = ();
+
+    fn from_form_items(items: &mut FormItems<'f>) -> std::result::Result<Self, ()> {
+        fn parse<'v, V: FromFormValue<'v>>(value: &'v str) -> std::result::Result<Option<V>, ()> {
+            V::from_form_value(value).map(Some).map_err(|_| ())
+        }
+
+        let mut page = None;
+        let mut serial_no = None;
+        let mut listing = ListingParams::default();
+        let mut rest = Vec::new();
+
+        for (key, value) in items {
+            match key {
+                "page" => page = parse(value)?,
+                "serial_no" => serial_no = Some(value),
+                "has_video" => listing.has_video = parse(value)?,
+                "has_series" => listing.has_series = parse(value)?,
+                "added_after" => listing.added_after = parse(value)?,
+                "added_before" => listing.added_before = parse(value)?,
+                "first_kana" => listing.first_kana = parse(value)?,
+                "sort" => listing.sort = parse(value)?,
+                "reverse" => listing.reverse = parse(value)?,
+                _ => rest.push(format!("{}={}", key, value)),
+            }
+        }
+
+        let rest = rest.join("&");
+        let route = T::from_form_items(&mut FormItems::from(rest.as_str())).map_err(|_| ())?;
+
+        Ok(SongParams {
+            page: page,
+            serial_no: serial_no,
+            listing: listing,
+            route: route,
+        })
+    }
+}
+
+struct SongListing {
+    filter: clubdarn::filter::SongFilter,
+    sort: Option<clubdarn::filter::SortKey>,
+    descending: bool,
+}
+
+impl SongListing {
+    // Invalid dates and sort keys are bad requests
+    fn new(params: &ListingParams) -> Result<Self> {
+        fn bad_request(e: clubdarn::Error) -> Error {
+            ErrorKind::BadRequest(e.to_string()).into()
+        }
+
+        let mut song_filter = clubdarn::filter::SongFilter::new();
+
+        if let Some(has_video) = params.has_video {
+            song_filter = song_filter.has_video(has_video);
+        }
+        if let Some(has_series) = params.has_series {
+            song_filter = song_filter.has_series(has_series);
+        }
+        if let Some(ref date) = params.added_after {
+            song_filter = song_filter.added_after(date).map_err(bad_request)?;
+        }
+        if let Some(ref date) = params.added_before {
+            song_filter = song_filter.added_before(date).map_err(bad_request)?;
+        }
+        if let Some(kana) = params.first_kana.as_ref().and_then(|k| k.chars().next()) {
+            song_filter = song_filter.first_kana(kana);
+        }
+
+        let sort = match params.sort {
+            Some(ref key) => {
+                Some(key.parse::<clubdarn::filter::SortKey>().map_err(bad_request)?)
+            }
+            None => None,
+        };
+
+        Ok(SongListing {
+            filter: song_filter,
+            sort: sort,
+            descending: params.reverse.unwrap_or(false),
+        })
+    }
+
+    fn is_active(&self) -> bool {
+        !self.filter.is_empty() || self.sort.is_some()
+    }
+
+    // Recommend API results are missing fields that filters and some sort keys use
+    fn needs_enrichment(&self) -> bool {
+        !self.filter.is_empty() || self.sort.map_or(false, |key| key.needs_details())
+    }
+
+    fn apply(&self, page: &mut clubdarn::Paginated<clubdarn::Song>) {
+        self.filter.apply(page);
+
+        if let Some(key) = self.sort {
+            clubdarn::filter::sort_songs(&mut page.items, key, self.descending);
+        }
+    }
+}
+
+// Unknown machine profile names and invalid serial numbers are bad requests
+fn resolve_machine(client: &clubdarn::Client, name_or_serial_no: &str) -> Result<String> {
+    client.machine(name_or_serial_no)
+        .map(|machine| machine.serial_no)
+        .map_err(|e| ErrorKind::BadRequest(e.to_string()).into())
+}
+
+// `serial_no` params can be machine profile names or serial numbers
+fn resolve_serial_no(client: &clubdarn::Client,
+                     serial_no: Option<&str>)
+                     -> Result<Option<String>> {
+    match serial_no {
+        Some(serial_no) => resolve_machine(client, serial_no).map(Some),
+        None => Ok(None),
+    }
+}
+
+// Song IDs are passed as a comma-separated list
+fn parse_song_ids(ids: &str) -> Result<Vec<u32>> {
+    ids.split(',')
+        .map(|id| id.trim().parse::<u32>())
+        .collect::<::std::result::Result<Vec<_>, _>>()
+        .map_err(|_| ErrorKind::BadRequest(format!("invalid song IDs {}", ids)).into())
+}
+
 macro_rules! request {
-    ($params:expr, $e:expr) => {{
+    ($client:expr, $params:expr, $e:expr) => {{
+        let serial_no = resolve_serial_no(&$client, $params.serial_no)?;
         let resp = $e.set_page($params.page.unwrap_or(1))
-            .set_serial_no($params.serial_no).send()?;
+            .set_serial_no(serial_no.as_ref().map(|s| s.as_str())).send()?;
         Ok(Cors(JSON(resp)))
     }}
 }
 
+// Filtering or sorting songs requires fetching every page, starting from `page`. Queries
+// spanning more than `MAX_FILTERED_PAGES` pages are rejected rather than crawled.
+macro_rules! song_request {
+    ($client:expr, $params:expr, $e:expr) => {{
+        let listing = SongListing::new(&$params.listing)?;
+
+        if listing.is_active() {
+            let serial_no = resolve_serial_no(&$client, $params.serial_no)?;
+            let mut resp = $e.set_page($params.page.unwrap_or(1))
+                .set_serial_no(serial_no.as_ref().map(|s| s.as_str()))
+                .send_all_limited(MAX_FILTERED_PAGES)?;
+            listing.apply(&mut resp);
+            Ok(Cors(JSON(resp)))
+        } else {
+            request!($client, $params, $e)
+        }
+    }}
+}
+
 macro_rules! cached_request {
-    ($duration:expr, $params:expr, $e:expr) => {{
-        request!($params, $e).map(|inner|
+    ($duration:expr, $client:expr, $params:expr, $e:expr) => {{
+        request!($client, $params, $e).map(|inner|
             Cached::new(inner, $duration)
         )
     }}
@@ -126,7 +329,7 @@ mod artists {
     use super::*;
 
     pub fn routes() -> Vec<Route> {
-        routes![by_name, songs, live]
+        routes![by_name, by_id, songs_by_name, songs, discography, live]
     }
 
     #[derive(FromForm)]
@@ -144,29 +347,108 @@ mod artists {
             _ => clubdarn::MatchType::Contains,
         };
 
-        request!(params, client.artists().by_name(&params.name, match_type))
+        request!(client, params, client.artists().by_name(&params.name, match_type))
     }
 
-    #[get("/live?<params>")]
-    fn live(client: ClientState, params: CommonParams) -> PageResult<clubdarn::Artist> {
-        request!(params, client.artists().live_performance())
+    // Ranked below static routes such as `/live`, which would otherwise collide
+    #[get("/<artist_id>?<params>", rank = 2)]
+    fn by_id(client: ClientState,
+             artist_id: u32,
+             params: CommonParams)
+             -> Result<Cors<JSON<clubdarn::artist::ArtistSongs>>> {
+        let serial_no = resolve_serial_no(&client, params.serial_no)?;
+        let found = client.artist_by_id(clubdarn::ArtistId(artist_id),
+                                        params.page.unwrap_or(1),
+                                        serial_no.as_ref().map(|s| s.as_str()))?;
+
+        match found {
+            Some(result) => Ok(Cors(JSON(result))),
+            None => Err(ErrorKind::NotFound(format!("no artist found with ID {}", artist_id)))?,
+        }
+    }
+
+    #[derive(Serialize)]
+    struct NameLookup {
+        artist: Option<clubdarn::Artist>,
+        candidates: Vec<clubdarn::Artist>,
+        songs: Option<clubdarn::Paginated<clubdarn::Song>>,
     }
 
     #[derive(FromForm)]
-    struct ByCategory<'a> {
-        category_id: Option<&'a str>,
+    struct SongsByName<'a> {
+        name: String,
         page: Option<u32>,
         serial_no: Option<&'a str>,
     }
 
+    // Ambiguous names return the candidate artists instead of songs
+    #[get("/songs?<params>")]
+    fn songs_by_name(client: ClientState, params: SongsByName) -> Result<Cors<JSON<NameLookup>>> {
+        use clubdarn::artist::ArtistMatch;
+
+        let serial_no = resolve_serial_no(&client, params.serial_no)?;
+        let found = client.songs_by_artist_name(&params.name,
+                                                params.page.unwrap_or(1),
+                                                serial_no.as_ref().map(|s| s.as_str()))?;
+
+        let lookup = match found {
+            ArtistMatch::Found(result) => {
+                NameLookup {
+                    artist: Some(result.artist),
+                    candidates: vec![],
+                    songs: Some(result.songs),
+                }
+            }
+            ArtistMatch::Ambiguous(candidates) => {
+                NameLookup {
+                    artist: None,
+                    candidates: candidates,
+                    songs: None,
+                }
+            }
+            ArtistMatch::NotFound => {
+                Err(ErrorKind::NotFound(format!("no artist found matching {}", params.name)))?
+            }
+        };
+
+        Ok(Cors(JSON(lookup)))
+    }
+
+    #[get("/live?<params>")]
+    fn live(client: ClientState, params: CommonParams) -> PageResult<clubdarn::Artist> {
+        request!(client, params, client.artists().live_performance())
+    }
+
+    #[derive(FromForm)]
+    struct ByCategory {
+        category_id: Option<String>,
+    }
+
     #[get("/<artist_id>/songs?<params>")]
     fn songs(client: ClientState,
              artist_id: u32,
-             params: ByCategory)
+             params: SongParams<ByCategory>)
              -> PageResult<clubdarn::Song> {
-        let category_id = params.category_id.unwrap_or(clubdarn::category::ARTIST_NAME.id.0);
-        request!(params,
-                 client.songs().by_artist_in_category_id(artist_id, category_id))
+        let category_id = params.route
+            .category_id
+            .as_ref()
+            .map_or(clubdarn::category::ARTIST_NAME.id.0, |id| id.as_str());
+
+        song_request!(client,
+                      params,
+                      client.songs().by_artist_in_category_id(artist_id, category_id))
+    }
+
+    #[get("/<artist_id>/discography?<params>")]
+    fn discography(client: ClientState,
+                   artist_id: u32,
+                   params: CommonParams)
+                   -> PageResult<clubdarn::artist::DiscographySong> {
+        let serial_no = resolve_serial_no(&client, params.serial_no)?;
+        let resp = client.artist_discography(clubdarn::ArtistId(artist_id),
+                                             serial_no.as_ref().map(|s| s.as_str()),
+                                             Some(MAX_FILTERED_PAGES))?;
+        Ok(Cors(JSON(resp)))
     }
 }
 
@@ -174,53 +456,144 @@ mod songs {
     use super::*;
 
     pub fn routes() -> Vec<Route> {
-        routes![by_id, by_name, similar, library, library_options]
+        routes![by_id, by_name, search, similar, similar_to_all, library, library_options]
     }
 
     #[derive(FromForm)]
-    struct ByTitle<'a> {
+    struct ByTitle {
         title: String,
         starts_with: Option<bool>,
-        page: Option<u32>,
-        serial_no: Option<&'a str>,
     }
 
     #[get("/?<params>")]
-    fn by_name(client: ClientState, params: ByTitle) -> PageResult<clubdarn::Song> {
-        let match_type = match params.starts_with {
+    fn by_name(client: ClientState, params: SongParams<ByTitle>) -> PageResult<clubdarn::Song> {
+        let match_type = match params.route.starts_with {
             Some(true) => clubdarn::MatchType::StartsWith,
             _ => clubdarn::MatchType::Contains,
         };
 
-        request!(params, client.songs().by_title(&params.title, match_type))
+        song_request!(client,
+                      params,
+                      client.songs().by_title(&params.route.title, match_type))
     }
 
-    #[get("/<song_id>?<params>")]
+    #[derive(FromForm)]
+    struct Search {
+        title: Option<String>,
+        title_starts_with: Option<bool>,
+        artist: Option<String>,
+        artist_starts_with: Option<bool>,
+        artist_id: Option<u32>,
+        series: Option<String>,
+        category_id: Option<String>,
+    }
+
+    fn match_type(starts_with: Option<bool>) -> clubdarn::MatchType {
+        match starts_with {
+            Some(true) => clubdarn::MatchType::StartsWith,
+            _ => clubdarn::MatchType::Contains,
+        }
+    }
+
+    #[get("/search?<params>")]
+    fn search(client: ClientState, params: SongParams<Search>) -> PageResult<clubdarn::Song> {
+        let mut query = clubdarn::SongQuery::new();
+        let search = &params.route;
+
+        if let Some(ref title) = search.title {
+            query = query.title(title, match_type(search.title_starts_with));
+        }
+        if let Some(ref artist) = search.artist {
+            query = query.artist_name(artist, match_type(search.artist_starts_with));
+        }
+        if let Some(artist_id) = search.artist_id {
+            query = query.artist_id(artist_id);
+        }
+        if let Some(ref series) = search.series {
+            query = query.series(series);
+        }
+        if let Some(ref category_id) = search.category_id {
+            query = query.category_id(category_id);
+        }
+
+        if query.is_empty() {
+            Err(ErrorKind::BadRequest("at least one search criterion is required".to_string()))?
+        }
+
+        song_request!(client, params, client.songs().by_query(query))
+    }
+
+    // Ranked below static routes such as `/search`, which would otherwise collide
+    #[get("/<song_id>?<params>", rank = 2)]
     fn by_id(client: ClientState,
              song_id: u32,
-             params: CommonParams)
+             params: SongParams)
              -> PageResult<clubdarn::Song> {
-        request!(params, client.songs().by_id(song_id))
+        song_request!(client, params, client.songs().by_id(song_id))
+    }
+
+    #[derive(FromForm)]
+    struct Similar {
+        enrich: Option<bool>,
     }
 
+    // The recommend API doesn't return fields such as `hasVideo`, so recommended songs
+    // are enriched if `enrich` is set, or if they're filtered or sorted by those fields
     #[get("/<song_id>/similar?<params>")]
     fn similar(client: ClientState,
                song_id: u32,
-               params: CommonParams)
+               params: SongParams<Similar>)
                -> PageResult<clubdarn::Song> {
-        request!(params, client.songs().similar_to(song_id))
+        let serial_no = resolve_serial_no(&client, params.serial_no)?;
+        let serial_no = serial_no.as_ref().map(|s| s.as_str());
+
+        let listing = SongListing::new(&params.listing)?;
+        let mut resp = client.songs().similar_to(song_id).set_serial_no(serial_no).send()?;
+
+        if params.route.enrich.unwrap_or(false) || listing.needs_enrichment() {
+            client.enrich(resp.items.iter_mut(), serial_no)?;
+        }
+        listing.apply(&mut resp);
+
+        Ok(Cors(JSON(resp)))
+    }
+
+    #[derive(FromForm)]
+    struct SimilarToAll {
+        ids: String,
+        enrich: Option<bool>,
+    }
+
+    #[get("/similar?<params>")]
+    fn similar_to_all(client: ClientState,
+                      params: SongParams<SimilarToAll>)
+                      -> PageResult<clubdarn::Song> {
+        let ids = parse_song_ids(&params.route.ids)?;
+
+        let serial_no = resolve_serial_no(&client, params.serial_no)?;
+        let serial_no = serial_no.as_ref().map(|s| s.as_str());
+
+        let listing = SongListing::new(&params.listing)?;
+        let mut resp = client.similar_to_all(&ids, serial_no)?;
+
+        if params.route.enrich.unwrap_or(false) || listing.needs_enrichment() {
+            client.enrich(resp.items.iter_mut(), serial_no)?;
+        }
+        listing.apply(&mut resp);
+
+        Ok(Cors(JSON(resp)))
     }
 
     #[allow(unused_variables)]
     #[route(OPTIONS, "/lookup?<params>")]
-    fn library_options(params: CommonParams) -> Cors<()> {
+    fn library_options(params: SongParams) -> Cors<()> {
         Cors(())
     }
 
     #[post("/lookup?<params>", format = "application/json", data = "<post_data>")]
     fn library(client: ClientState,
                post_data: JSON<Vec<clubdarn::TitleAndArtist>>,
-               params: CommonParams)
+               params: SongParams)
                -> PageResult<clubdarn::Song> {
         if post_data.is_empty() {
             // TODO: Add a separate constructor function for `Paginated`
@@ -233,7 +606,128 @@ mod songs {
                 items: Vec::new(),
             })))
         } else {
-            request!(params, client.songs().by_titles_and_artists(&post_data))
+            song_request!(client, params, client.songs().by_titles_and_artists(&post_data))
+        }
+    }
+}
+
+mod compare {
+    use super::*;
+    use clubdarn::compare::Availability;
+
+    pub fn routes() -> Vec<Route> {
+        routes![songs]
+    }
+
+    #[derive(FromForm)]
+    struct Compare {
+        serial_nos: String,
+        ids: Option<String>,
+        title: Option<String>,
+        starts_with: Option<bool>,
+        page: Option<u32>,
+    }
+
+    // Either `ids` or `title` must be provided, along with a comma-separated
+    // list of serial numbers or machine profile names
+    #[get("/?<params>")]
+    fn songs(client: ClientState, params: Compare) -> Result<Cors<JSON<Availability>>> {
+        let serial_nos = params.serial_nos
+            .split(',')
+            .map(|s| resolve_machine(&client, s.trim()))
+            .collect::<Result<Vec<_>>>()?;
+        let serial_nos = serial_nos.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+        let availability = match (params.ids, params.title) {
+            (Some(ids), _) => {
+                client.compare_ids(&parse_song_ids(&ids)?, &serial_nos)?
+            }
+            (None, Some(title)) => {
+                let match_type = match params.starts_with {
+                    Some(true) => clubdarn::MatchType::StartsWith,
+                    _ => clubdarn::MatchType::Contains,
+                };
+
+                let mut request = client.songs().by_title(&title, match_type);
+                request.set_page(params.page.unwrap_or(1));
+                client.compare_request(&mut request, &serial_nos)?
+            }
+            (None, None) => {
+                Err(ErrorKind::BadRequest("either `ids` or `title` is required".to_string()))?
+            }
+        };
+
+        Ok(Cors(JSON(availability)))
+    }
+}
+
+mod rankings {
+    use super::*;
+    use clubdarn::ranking::{History, Movements, Ranking};
+    use std::fs::{self, File};
+    use std::io::BufReader;
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    pub struct HistoryFile {
+        path: String,
+        // The parsed history, along with the file's modification time and length when read
+        cached: Mutex<Option<(SystemTime, u64, Arc<History>)>>,
+    }
+
+    impl HistoryFile {
+        pub fn new(path: String) -> Self {
+            HistoryFile {
+                path: path,
+                cached: Mutex::new(None),
+            }
+        }
+
+        // The file is appended to by a separate process, so it's parsed again
+        // whenever its modification time or length changes
+        fn read(&self) -> Result<Arc<History>> {
+            let metadata = fs::metadata(&self.path)
+                .chain_err(|| format!("failed to read {}", self.path))?;
+            let modified = metadata.modified()
+                .chain_err(|| format!("failed to read the modification time of {}", self.path))?;
+            let len = metadata.len();
+
+            let mut cached = self.cached.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some((cached_modified, cached_len, ref history)) = *cached {
+                if cached_modified == modified && cached_len == len {
+                    return Ok(history.clone());
+                }
+            }
+
+            let file = File::open(&self.path)
+                .chain_err(|| format!("failed to open {}", self.path))?;
+            let history = Arc::new(History::read(BufReader::new(file))?);
+            *cached = Some((modified, len, history.clone()));
+
+            Ok(history)
+        }
+    }
+
+    pub fn routes() -> Vec<Route> {
+        routes![history, movements]
+    }
+
+    #[get("/<category_id>")]
+    fn history(file: State<HistoryFile>, category_id: &str) -> Result<Cors<JSON<Vec<Ranking>>>> {
+        let history = file.read()?;
+        let rankings = history.category(category_id).into_iter().cloned().collect();
+
+        Ok(Cors(JSON(rankings)))
+    }
+
+    #[get("/<category_id>/movements")]
+    fn movements(file: State<HistoryFile>, category_id: &str) -> Result<Cors<JSON<Movements>>> {
+        match file.read()?.movements(category_id) {
+            Some(movements) => Ok(Cors(JSON(movements))),
+            None => {
+                Err(ErrorKind::NotFound(format!("no ranking history for category {}",
+                                                category_id)))?
+            }
         }
     }
 }
@@ -268,6 +762,7 @@ mod categories {
               params: CommonParams)
               -> CachedPageResult<clubdarn::Series> {
         cached_request!(CATEGORY_CACHE_TTL_SECONDS,
+                        client,
                         params,
                         client.series().by_category_id(category_id))
     }
@@ -276,21 +771,21 @@ mod categories {
     fn series_songs(client: ClientState,
                     category_id: &str,
                     series_title: String,
-                    params: CommonParams)
+                    params: SongParams)
                     -> CachedPageResult<clubdarn::Song> {
-        cached_request!(CATEGORY_CACHE_TTL_SECONDS,
-                        params,
-                        client.songs().by_series_in_category_id(&series_title, category_id))
+        song_request!(client,
+                      params,
+                      client.songs().by_series_in_category_id(&series_title, category_id))
+            .map(|inner| Cached::new(inner, CATEGORY_CACHE_TTL_SECONDS))
     }
 
     #[get("/<category_id>/songs?<params>")]
     fn songs(client: ClientState,
              category_id: &str,
-             params: CommonParams)
+             params: SongParams)
              -> CachedPageResult<clubdarn::Song> {
-        cached_request!(CATEGORY_CACHE_TTL_SECONDS,
-                        params,
-                        client.songs().by_category_id(category_id))
+        song_request!(client, params, client.songs().by_category_id(category_id))
+            .map(|inner| Cached::new(inner, CATEGORY_CACHE_TTL_SECONDS))
     }
 
     #[derive(Clone, Serialize)]
@@ -0,0 +1,242 @@
+use category;
+use client::{Client, MatchType};
+use error::*;
+use model::{Artist, ArtistId, Paginated, Song, SongId};
+use std::collections::HashMap;
+use util;
+
+// Pages of artists searched when resolving an artist by name
+pub const MAX_ARTIST_PAGES: u32 = 5;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DiscographySong {
+    pub song: Song,
+    #[serde(rename = "hasLivePerformance")]
+    pub has_live_performance: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ArtistSongs {
+    pub artist: Artist,
+    pub songs: Paginated<Song>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArtistMatch {
+    Found(ArtistSongs),
+    Ambiguous(Vec<Artist>),
+    NotFound,
+}
+
+// Picks the artist whose name matches exactly (ignoring case, whitespace and full-width
+// characters). Otherwise the exact matches, or all of the candidates if none match exactly,
+// are returned to choose from.
+fn pick_artist(name: &str, candidates: Vec<Artist>) -> ::std::result::Result<Artist, Vec<Artist>> {
+    let normalized = util::normalize(name);
+
+    let (mut exact, others): (Vec<_>, Vec<_>) = candidates.into_iter()
+        .partition(|artist| util::normalize(&artist.name) == normalized);
+
+    if exact.len() == 1 {
+        Ok(exact.remove(0))
+    } else if !exact.is_empty() {
+        Err(exact)
+    } else {
+        Err(others)
+    }
+}
+
+fn find_artist(songs: &[Song], artist_id: ArtistId) -> Option<Artist> {
+    songs.iter()
+        .find(|song| song.artist.id == artist_id)
+        .map(|song| song.artist.clone())
+}
+
+impl<'a> Client<'a> {
+    // Finds an artist by name and fetches a page of their songs. Every page of artists whose
+    // names start with `name` is searched, so names matching more than `MAX_ARTIST_PAGES`
+    // pages of artists (e.g., a single character) fail with `ErrorKind::TooManyPages`.
+    // If `serial_no` is `None`, the client's default serial number is used.
+    pub fn songs_by_artist_name(&self,
+                                name: &str,
+                                page: u32,
+                                serial_no: Option<&str>)
+                                -> Result<ArtistMatch> {
+        let mut artists_request = self.artists().by_name(name, MatchType::StartsWith);
+        if serial_no.is_some() {
+            artists_request.set_serial_no(serial_no);
+        }
+
+        let candidates = artists_request.send_all_limited(MAX_ARTIST_PAGES)?.items;
+        if candidates.is_empty() {
+            return Ok(ArtistMatch::NotFound);
+        }
+
+        let artist = match pick_artist(name, candidates) {
+            Ok(artist) => artist,
+            Err(candidates) => return Ok(ArtistMatch::Ambiguous(candidates)),
+        };
+
+        let mut songs_request = self.songs().by_artist_id(artist.id.0);
+        songs_request.set_page(page);
+        if serial_no.is_some() {
+            songs_request.set_serial_no(serial_no);
+        }
+
+        Ok(ArtistMatch::Found(ArtistSongs {
+            songs: songs_request.send()?,
+            artist: artist,
+        }))
+    }
+
+    // Fetches a page of an artist's songs, taking the artist's details from the songs
+    // themselves since there's no way to look up an artist directly by ID. A page past
+    // the end has no songs to take them from, so the first page is fetched instead.
+    // Returns `None` if the artist has no songs at all.
+    pub fn artist_by_id(&self,
+                        artist_id: ArtistId,
+                        page: u32,
+                        serial_no: Option<&str>)
+                        -> Result<Option<ArtistSongs>> {
+        let mut songs_request = self.songs().by_artist_id(artist_id.0);
+        songs_request.set_page(page);
+        if serial_no.is_some() {
+            songs_request.set_serial_no(serial_no);
+        }
+
+        let songs = songs_request.send()?;
+
+        let mut artist = find_artist(&songs.items, artist_id);
+        if artist.is_none() && page != 1 {
+            artist = find_artist(&songs_request.set_page(1).send()?.items, artist_id);
+        }
+
+        Ok(artist.map(|artist| {
+            ArtistSongs {
+                artist: artist,
+                songs: songs,
+            }
+        }))
+    }
+
+    // Collects every page of an artist's songs, including live performances. Live versions
+    // have their own song IDs, so songs are marked as having a live performance when a
+    // live version has the same title (ignoring case, whitespace and full-width characters).
+    // If `max_pages` is set, artists with more pages of either kind of song than that fail
+    // with `ErrorKind::TooManyPages`. If `serial_no` is `None`, the client's default serial
+    // number is used.
+    pub fn artist_discography(&self,
+                              artist_id: ArtistId,
+                              serial_no: Option<&str>,
+                              max_pages: Option<u32>)
+                              -> Result<Paginated<DiscographySong>> {
+        let mut songs_request = self.songs().by_artist_id(artist_id.0);
+        let mut live_request = self.songs()
+            .by_artist_in_category_id(artist_id.0, category::LIVE_PERFORMANCE.id.0);
+
+        if serial_no.is_some() {
+            songs_request.set_serial_no(serial_no);
+            live_request.set_serial_no(serial_no);
+        }
+
+        let (songs, live_songs) = match max_pages {
+            Some(max_pages) => {
+                (songs_request.send_all_limited(max_pages)?,
+                 live_request.send_all_limited(max_pages)?)
+            }
+            None => (songs_request.send_all()?, live_request.send_all()?),
+        };
+
+        let mut items = Vec::with_capacity(songs.items.len() + live_songs.items.len());
+        let mut positions: HashMap<SongId, usize> = HashMap::new();
+        let mut titles: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for song in songs.items {
+            if positions.contains_key(&song.id) {
+                continue;
+            }
+
+            let position = items.len();
+            positions.insert(song.id, position);
+            titles.entry(util::normalize(&song.title)).or_insert_with(Vec::new).push(position);
+            items.push(DiscographySong {
+                song: song,
+                has_live_performance: false,
+            });
+        }
+
+        for song in live_songs.items {
+            if let Some(studio_positions) = titles.get(&util::normalize(&song.title)) {
+                for &position in studio_positions {
+                    items[position].has_live_performance = true;
+                }
+            }
+
+            let position = positions.get(&song.id).cloned();
+
+            match position {
+                Some(position) => items[position].has_live_performance = true,
+                None => {
+                    positions.insert(song.id, items.len());
+                    items.push(DiscographySong {
+                        song: song,
+                        has_live_performance: true,
+                    });
+                }
+            }
+        }
+
+        Ok(Paginated {
+            page: 1,
+            artist_category_id: songs.artist_category_id,
+            series_category_id: None,
+            total_items: items.len() as u32,
+            total_pages: 1,
+            items: items,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artist(id: u32, name: &str) -> Artist {
+        Artist {
+            id: ArtistId(id),
+            name: name.to_string(),
+            first_kana: None,
+        }
+    }
+
+    fn ids(artists: &[Artist]) -> Vec<u32> {
+        artists.iter().map(|artist| artist.id.0).collect()
+    }
+
+    #[test]
+    fn exact_name_among_others() {
+        let candidates = vec![artist(1, "Perfume Genius"), artist(2, "ＰＥＲＦＵＭＥ")];
+        assert_eq!(pick_artist("perfume", candidates).map(|a| a.id.0), Ok(2));
+    }
+
+    #[test]
+    fn lone_inexact_result() {
+        let candidates = vec![artist(1, "Perfume Genius")];
+        assert_eq!(pick_artist("Perfume", candidates).map_err(|a| ids(&a)), Err(vec![1]));
+    }
+
+    #[test]
+    fn ambiguous() {
+        let candidates = vec![artist(1, "Perfume Genius"), artist(2, "Perfume Girls")];
+        assert_eq!(pick_artist("Perfume", candidates).map_err(|a| ids(&a)), Err(vec![1, 2]));
+
+        // Only the exact matches are offered when there are several
+        let candidates = vec![artist(1, "Lamp"), artist(2, "Lamp"), artist(3, "Lamplight")];
+        assert_eq!(pick_artist("lamp", candidates).map_err(|a| ids(&a)), Err(vec![1, 2]));
+    }
+
+    #[test]
+    fn no_results() {
+        assert_eq!(pick_artist("Perfume", vec![]).map_err(|a| a.len()), Err(0));
+    }
+}
@@ -0,0 +1,104 @@
+// Responses are cached as files of JSON, one per request, named after a hash of the
+// request's URL and parameters. Entries older than the cache's TTL are treated as missing,
+// and are overwritten by the next response to the same request.
+
+use error::*;
+use serde_json::{self, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new<P: Into<PathBuf>>(dir: P, ttl: Duration) -> Self {
+        ResponseCache {
+            dir: dir.into(),
+            ttl: ttl,
+        }
+    }
+
+    // `DefaultHasher::new` always uses the same keys, so the names are stable across runs
+    fn path(&self, url: &str, params: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        params.hash(&mut hasher);
+
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    // Expired entries, and ones that can't be read (e.g., while being written), are misses
+    pub fn get(&self, url: &str, params: &str) -> Option<Value> {
+        let path = self.path(url, params);
+
+        let modified = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return None,
+        };
+
+        match SystemTime::now().duration_since(modified) {
+            Ok(age) if age < self.ttl => {}
+            _ => return None,
+        }
+
+        File::open(&path)
+            .ok()
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+    }
+
+    pub fn insert(&self, url: &str, params: &str, body: &Value) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .chain_err(|| format!("failed to create cache directory {}", self.dir.display()))?;
+
+        let path = self.path(url, params);
+        let file = File::create(&path)
+            .chain_err(|| format!("failed to create cache entry {}", path.display()))?;
+
+        Ok(serde_json::to_writer(&mut BufWriter::new(file), body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn cache(name: &str, ttl: u64) -> ResponseCache {
+        let dir = env::temp_dir().join(format!("clubdarn-cache-test-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+
+        ResponseCache::new(dir, Duration::from_secs(ttl))
+    }
+
+    fn json(input: &str) -> Value {
+        serde_json::from_str(input).unwrap()
+    }
+
+    #[test]
+    fn hit_for_same_request() {
+        let cache = cache("hit", 3600);
+        let body = json(r#"{"list": []}"#);
+
+        cache.insert("https://example.com", r#"{"page":1}"#, &body).unwrap();
+
+        assert_eq!(cache.get("https://example.com", r#"{"page":1}"#), Some(body));
+        assert_eq!(cache.get("https://example.com", r#"{"page":2}"#), None);
+        assert_eq!(cache.get("https://example.org", r#"{"page":1}"#), None);
+    }
+
+    #[test]
+    fn expired_entries_miss() {
+        let cache = cache("expired", 0);
+
+        cache.insert("https://example.com", "{}", &json("{}")).unwrap();
+
+        assert_eq!(cache.get("https://example.com", "{}"), None);
+    }
+}
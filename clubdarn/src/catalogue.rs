@@ -0,0 +1,115 @@
+use client::{Client, MatchType};
+use error::*;
+use model::{Artist, Paginated, Series, Song};
+
+// Queries that can be answered either by the ClubDAM API (`Client`)
+// or by a snapshot on disk (`local::LocalIndex`)
+pub trait Catalogue {
+    fn songs_by_title(&self,
+                      title: &str,
+                      match_type: MatchType,
+                      page: u32)
+                      -> Result<Paginated<Song>>;
+
+    fn songs_by_artist_in_category_id(&self,
+                                      artist_id: u32,
+                                      category_id: &str,
+                                      page: u32)
+                                      -> Result<Paginated<Song>>;
+
+    fn songs_by_series_in_category_id(&self,
+                                      title: &str,
+                                      category_id: &str,
+                                      page: u32)
+                                      -> Result<Paginated<Song>>;
+
+    fn songs_by_category_id(&self, category_id: &str, page: u32) -> Result<Paginated<Song>>;
+
+    fn artists_by_name(&self,
+                       name: &str,
+                       match_type: MatchType,
+                       page: u32)
+                       -> Result<Paginated<Artist>>;
+
+    fn series_by_category_id(&self, category_id: &str, page: u32) -> Result<Paginated<Series>>;
+}
+
+impl<'a> Catalogue for Client<'a> {
+    fn songs_by_title(&self,
+                      title: &str,
+                      match_type: MatchType,
+                      page: u32)
+                      -> Result<Paginated<Song>> {
+        self.songs().by_title(title, match_type).set_page(page).send()
+    }
+
+    fn songs_by_artist_in_category_id(&self,
+                                      artist_id: u32,
+                                      category_id: &str,
+                                      page: u32)
+                                      -> Result<Paginated<Song>> {
+        self.songs().by_artist_in_category_id(artist_id, category_id).set_page(page).send()
+    }
+
+    fn songs_by_series_in_category_id(&self,
+                                      title: &str,
+                                      category_id: &str,
+                                      page: u32)
+                                      -> Result<Paginated<Song>> {
+        self.songs().by_series_in_category_id(title, category_id).set_page(page).send()
+    }
+
+    fn songs_by_category_id(&self, category_id: &str, page: u32) -> Result<Paginated<Song>> {
+        self.songs().by_category_id(category_id).set_page(page).send()
+    }
+
+    fn artists_by_name(&self,
+                       name: &str,
+                       match_type: MatchType,
+                       page: u32)
+                       -> Result<Paginated<Artist>> {
+        self.artists().by_name(name, match_type).set_page(page).send()
+    }
+
+    fn series_by_category_id(&self, category_id: &str, page: u32) -> Result<Paginated<Series>> {
+        self.series().by_category_id(category_id).set_page(page).send()
+    }
+}
+
+// Iterates over pages fetched by `fetch`, starting from `first_page`,
+// similar to `RequestBuilder::pages`
+pub fn pages<T, F>(first_page: u32, fetch: F) -> Pages<F>
+    where F: FnMut(u32) -> Result<Paginated<T>>
+{
+    Pages {
+        fetch: fetch,
+        next_page: Some(first_page),
+    }
+}
+
+pub struct Pages<F> {
+    fetch: F,
+    next_page: Option<u32>,
+}
+
+impl<T, F> Iterator for Pages<F>
+    where F: FnMut(u32) -> Result<Paginated<T>>
+{
+    type Item = Result<Paginated<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page_num = match self.next_page {
+            Some(page_num) => page_num,
+            None => return None,
+        };
+
+        let result = (self.fetch)(page_num);
+
+        self.next_page = match result {
+            Ok(ref page) => page.next_page(),
+            Err(_) => None,
+        };
+
+        Some(result)
+    }
+}
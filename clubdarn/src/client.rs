@@ -1,18 +1,26 @@
 extern crate serde_json;
 extern crate reqwest;
 
+use cache::ResponseCache;
 use category;
 use category::*;
 use error::*;
+use machine::{Machine, MachineProfiles};
 use model::*;
 use protocol::{api, exist, recommend, search};
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
 pub struct Client<'a> {
     http: Arc<reqwest::Client>,
     meta: Metadata<'a>,
+    recommend: RecommendConfig,
+    // Overrides `meta.serial_no`, since the serial numbers of machines are owned
+    default_machine: Option<Machine>,
+    machines: MachineProfiles,
+    cache: Option<Arc<ResponseCache>>,
 }
 
 pub struct Metadata<'a> {
@@ -35,7 +43,32 @@ impl Default for Metadata<'static> {
     }
 }
 
-pub struct Pending<'a>(&'a Metadata<'a>);
+// Parameters sent to the recommend API, which uses different credentials from the others
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecommendConfig {
+    pub comp_id: i8,
+    pub contract_id: i8,
+    pub comp_auth_key: String,
+    pub format: String,
+}
+
+impl Default for RecommendConfig {
+    fn default() -> Self {
+        // Same values as the ones hardcoded into the Denmoku app
+        RecommendConfig {
+            comp_id: 1,
+            contract_id: 1,
+            comp_auth_key: "2/Qb9R@8s*".into(),
+            format: "json".into(),
+        }
+    }
+}
+
+pub struct Pending<'a> {
+    meta: &'a Metadata<'a>,
+    serial_no: Option<&'a str>,
+    recommend: &'a RecommendConfig,
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TitleAndArtist<'a> {
@@ -43,6 +76,7 @@ pub struct TitleAndArtist<'a> {
     pub artist: Cow<'a, str>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MatchType {
     StartsWith,
     Contains,
@@ -57,6 +91,56 @@ impl From<MatchType> for &'static str {
     }
 }
 
+// Criteria that can be combined into a single song search request.
+// Unset fields are left out of the request.
+#[derive(Default)]
+pub struct SongQuery<'a> {
+    title: Option<(&'a str, MatchType)>,
+    artist_name: Option<(&'a str, MatchType)>,
+    artist_id: Option<u32>,
+    series_title: Option<&'a str>,
+    category_id: Option<&'a str>,
+}
+
+impl<'a> SongQuery<'a> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn title(mut self, title: &'a str, match_type: MatchType) -> Self {
+        self.title = Some((title, match_type));
+        self
+    }
+
+    pub fn artist_name(mut self, name: &'a str, match_type: MatchType) -> Self {
+        self.artist_name = Some((name, match_type));
+        self
+    }
+
+    pub fn artist_id(mut self, id: u32) -> Self {
+        self.artist_id = Some(id);
+        self
+    }
+
+    pub fn series(mut self, title: &'a str) -> Self {
+        self.series_title = Some(title);
+        self
+    }
+
+    pub fn category_id(mut self, category_id: &'a str) -> Self {
+        self.category_id = Some(category_id);
+        self
+    }
+
+    pub fn category<T: CategoryType>(self, category: Category<T>) -> Self {
+        self.category_id(category.id.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.artist_name.is_none() && self.artist_id.is_none() &&
+        self.series_title.is_none() && self.category_id.is_none()
+    }
+}
 
 impl<'a> Client<'a> {
     pub fn new(meta: Metadata<'a>) -> Result<Self> {
@@ -65,6 +149,10 @@ impl<'a> Client<'a> {
         Ok(Client {
             http: Arc::new(http),
             meta: meta,
+            recommend: RecommendConfig::default(),
+            default_machine: None,
+            machines: MachineProfiles::new(),
+            cache: None,
         })
     }
 
@@ -75,32 +163,176 @@ impl<'a> Client<'a> {
     fn request_builder<T, U>(&self, req: T) -> RequestBuilder<T, U> {
         RequestBuilder {
             http: self.http.clone(),
+            cache: self.cache.clone(),
             request: req,
             response_item_type: PhantomData,
         }
     }
 
+    // Responses are taken from `cache` while they're fresh, instead of sending requests
+    pub fn set_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
     pub fn set_default_serial_no(mut self, serial_no: Option<&'a str>) -> Self {
         self.meta.serial_no = serial_no;
+        self.default_machine = None;
+        self
+    }
+
+    pub fn default_serial_no(&self) -> Option<&str> {
+        match self.default_machine {
+            Some(ref machine) => Some(&machine.serial_no),
+            None => self.meta.serial_no,
+        }
+    }
+
+    pub fn set_default_machine(mut self, machine: Machine) -> Self {
+        self.default_machine = Some(machine);
+        self
+    }
+
+    pub fn set_machine_profiles(mut self, machines: MachineProfiles) -> Self {
+        self.machines = machines;
+        self
+    }
+
+    pub fn machine_profiles(&self) -> &MachineProfiles {
+        &self.machines
+    }
+
+    // Resolves a machine profile name or a raw serial number
+    pub fn machine(&self, name_or_serial_no: &str) -> Result<Machine> {
+        self.machines.resolve(name_or_serial_no)
+    }
+
+    pub fn set_recommend_config(mut self, config: RecommendConfig) -> Self {
+        self.recommend = config;
         self
     }
 
+    pub fn recommend_config(&self) -> &RecommendConfig {
+        &self.recommend
+    }
+
+    fn pending(&self) -> Pending {
+        Pending {
+            meta: &self.meta,
+            serial_no: self.default_serial_no(),
+            recommend: &self.recommend,
+        }
+    }
+
     pub fn artists(&self) -> RequestBuilder<Pending, Artist> {
-        self.request_builder(Pending(&self.meta))
+        self.request_builder(self.pending())
     }
 
     pub fn songs(&self) -> RequestBuilder<Pending, Song> {
-        self.request_builder(Pending(&self.meta))
+        self.request_builder(self.pending())
     }
 
     pub fn series(&self) -> RequestBuilder<Pending, Series> {
-        self.request_builder(Pending(&self.meta))
+        self.request_builder(self.pending())
+    }
+
+    pub fn recommendations(&self) -> RequestBuilder<Pending, RecommendedSong> {
+        self.request_builder(self.pending())
+    }
+
+    // Some APIs (e.g., recommend) return songs with missing fields. This looks up
+    // the songs by ID using the exist API and fills in whatever is available.
+    // If `serial_no` is `None`, the client's default serial number is used.
+    pub fn enrich<'s, S>(&self, songs: S, serial_no: Option<&str>) -> Result<()>
+        where S: IntoIterator<Item = &'s mut Song>
+    {
+        let mut songs = songs.into_iter().collect::<Vec<_>>();
+        if songs.is_empty() {
+            return Ok(());
+        }
+
+        let ids = songs.iter().map(|song| song.id.0).collect::<Vec<_>>();
+
+        let mut request = self.songs().by_ids(&ids);
+        if serial_no.is_some() {
+            request.set_serial_no(serial_no);
+        }
+
+        let details = request.send()?
+            .items
+            .into_iter()
+            .map(|song| (song.id, song))
+            .collect::<HashMap<_, _>>();
+
+        for song in songs.iter_mut() {
+            if let Some(detail) = details.get(&song.id) {
+                song.has_video = song.has_video || detail.has_video;
+
+                if song.date_added.is_none() {
+                    song.date_added = detail.date_added.clone();
+                }
+                if song.end_date.is_none() {
+                    song.end_date = detail.end_date.clone();
+                }
+                if song.lyrics.is_none() {
+                    song.lyrics = detail.lyrics.clone();
+                }
+                if song.series.is_none() {
+                    song.series = detail.series.clone();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Sends a recommend request per seed song, and merges the results so that songs
+    // recommended for the most seeds come first. Seed songs are excluded from the results.
+    // If `serial_no` is `None`, the client's default serial number is used.
+    pub fn similar_to_all(&self,
+                          song_ids: &[u32],
+                          serial_no: Option<&str>)
+                          -> Result<Paginated<Song>> {
+        let mut songs: Vec<Song> = Vec::new();
+        let mut seed_counts: HashMap<SongId, u32> = HashMap::new();
+
+        for song_id in song_ids {
+            let mut request = self.songs().similar_to(*song_id);
+            if serial_no.is_some() {
+                request.set_serial_no(serial_no);
+            }
+
+            for song in request.send()?.items {
+                if song_ids.contains(&song.id.0) {
+                    continue;
+                }
+
+                let count = seed_counts.entry(song.id).or_insert(0);
+                if *count == 0 {
+                    songs.push(song);
+                }
+                *count += 1;
+            }
+        }
+
+        // The sort is stable, so songs with equal counts stay in the order they were found
+        songs.sort_by(|a, b| seed_counts[&b.id].cmp(&seed_counts[&a.id]));
+
+        Ok(Paginated {
+            page: 1,
+            artist_category_id: category::ARTIST_NAME.id.0.into(),
+            series_category_id: None,
+            total_items: songs.len() as u32,
+            total_pages: 1,
+            items: songs,
+        })
     }
 }
 
 #[must_use = "RequestBuilder does nothing until you call `send`"]
 pub struct RequestBuilder<RequestT, ResponseItemT> {
     http: Arc<reqwest::Client>,
+    cache: Option<Arc<ResponseCache>>,
     request: RequestT,
     response_item_type: PhantomData<ResponseItemT>,
 }
@@ -109,9 +341,13 @@ impl<'a, I> RequestBuilder<Pending<'a>, I> {
     fn default_request<R>(&self) -> RequestBuilder<R, I>
         where R: api::Request<'a>
     {
+        let mut request = R::from_client_metadata(self.request.meta);
+        request.set_serial_no(self.request.serial_no);
+
         RequestBuilder {
             http: self.http.clone(),
-            request: R::from_client_metadata(self.request.0),
+            cache: self.cache.clone(),
+            request: request,
             response_item_type: PhantomData,
         }
     }
@@ -130,6 +366,38 @@ impl<'a> RequestBuilder<Pending<'a>, Song> {
         req
     }
 
+    pub fn by_query(self, query: SongQuery<'a>) -> RequestBuilder<search::Request<'a>, Song> {
+        let mut req = self.default_request::<search::Request>();
+
+        // Searching by title, artist and series use different categories. If several are
+        // specified, the title category is used, then the artist's, since the others are
+        // then filters. There's no category for every series, so searching only by series
+        // uses the anime one unless a category is given.
+        let has_artist = query.artist_name.is_some() || query.artist_id.is_some();
+        let default_category = if query.title.is_some() {
+            category::SONG_NAME.id.0
+        } else if !has_artist && query.series_title.is_some() {
+            category::series::ANIME.id.0
+        } else {
+            category::ARTIST_NAME.id.0
+        };
+
+        if let Some((title, match_type)) = query.title {
+            req.request.song_name = Some(title);
+            req.request.song_match_type = Some(match_type.into());
+        }
+
+        if let Some((name, match_type)) = query.artist_name {
+            req.request.artist_name = Some(name);
+            req.request.artist_match_type = Some(match_type.into());
+        }
+
+        req.request.artist_id = query.artist_id;
+        req.request.program_title = query.series_title;
+        req.request.category_cd = query.category_id.unwrap_or(default_category);
+        req
+    }
+
     pub fn starting_with(self, title: &'a str) -> RequestBuilder<search::Request, Song> {
         self.by_title(title, MatchType::StartsWith)
     }
@@ -212,17 +480,27 @@ impl<'a> RequestBuilder<Pending<'a>, Song> {
         req.request.is_exist = vec![item];
         req
     }
+}
 
-    pub fn similar_to(self, song_id: u32) -> RequestBuilder<recommend::Request<'a>, Song> {
-        let mut req = self.default_request::<recommend::Request>();
-        let mut song_id_str = song_id.to_string();
+// Recommendations can be returned as either `Song` or `RecommendedSong`
+impl<'a, I> RequestBuilder<Pending<'a>, I>
+    where I: From<recommend::Item>
+{
+    pub fn similar_to(self, song_id: u32) -> RequestBuilder<recommend::Request<'a>, I> {
+        self.similar_to_any(&[song_id])
+    }
 
-        // The recommend API requires song IDs to be in the format "1234-56"
-        if (song_id_str.len() as u32) > 4 {
-            song_id_str.insert(4, '-');
-        }
+    // Sends all seeds in a single request. Use `Client::similar_to_all`
+    // to find out which songs are recommended for multiple seeds.
+    pub fn similar_to_any(self, song_ids: &[u32]) -> RequestBuilder<recommend::Request<'a>, I> {
+        let mut req = self.default_request::<recommend::Request>();
+        req.request.set_config(self.request.recommend);
 
-        req.request.request_no_list = song_id_str.into();
+        req.request.request_no_list = song_ids.iter()
+            .map(|id| SongId(*id).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+            .into();
         req
     }
 }
@@ -288,7 +566,7 @@ impl<'a, R, I> RequestBuilder<R, I>
     where R: api::Request<'a>,
           I: From<<R::ResponseType as api::Response>::ItemType>
 {
-    fn make_request(&'a self) -> reqwest::RequestBuilder {
+    fn make_request(&self) -> reqwest::RequestBuilder {
         let request = self.http.post(R::url());
 
         match R::request_type() {
@@ -297,16 +575,44 @@ impl<'a, R, I> RequestBuilder<R, I>
         }
     }
 
-    pub fn send(&'a self) -> Result<Paginated<I>> {
-        use protocol::api::Response;
-
+    fn fetch(&self) -> Result<serde_json::Value> {
         // TODO: Use enum errors
         // We have to retry here due to periodic "Connection closed" errors.
         // See: https://github.com/seanmonstar/reqwest/issues/44
-        let response: R::ResponseType = self.make_request().send()
+        let mut http_response = self.make_request().send()
             .or_else(|_| self.make_request().send())
-            .chain_err(|| "failed to send request")?
-            .json()
+            .chain_err(|| "failed to send request")?;
+
+        R::check_status(http_response.status())?;
+
+        let body: serde_json::Value = http_response.json()
+            .chain_err(|| "failed to parse JSON response")?;
+        R::check_body(&body)?;
+
+        Ok(body)
+    }
+
+    pub fn send(&self) -> Result<Paginated<I>> {
+        use protocol::api::Response;
+
+        let body = match self.cache {
+            Some(ref cache) => {
+                let params = serde_json::to_string(&self.request)?;
+
+                match cache.get(R::url(), &params) {
+                    Some(body) => body,
+                    None => {
+                        let body = self.fetch()?;
+                        // Failing to cache a response doesn't fail the request
+                        let _ = cache.insert(R::url(), &params, &body);
+                        body
+                    }
+                }
+            }
+            None => self.fetch()?,
+        };
+
+        let response: R::ResponseType = serde_json::from_value(body)
             .chain_err(|| "failed to parse JSON response")?;
 
         let artist_category_id = self.request
@@ -335,4 +641,116 @@ impl<'a, R, I> RequestBuilder<R, I>
 
         Ok(body)
     }
+
+    // Iterates over every page, starting from the currently set page
+    pub fn pages(&mut self) -> Pages<R, I> {
+        let page = self.request.page();
+
+        Pages {
+            builder: self,
+            next_page: Some(page),
+        }
+    }
+
+    pub fn send_all(&mut self) -> Result<Paginated<I>> {
+        Paginated::from_pages(self.pages())
+    }
+
+    // Like `send_all`, but fails after the first page if fetching the rest would take
+    // more than `max_pages` requests in total
+    pub fn send_all_limited(&mut self, max_pages: u32) -> Result<Paginated<I>> {
+        let mut pages = self.pages();
+
+        let first = match pages.next() {
+            Some(page) => page?,
+            None => Err("no pages to fetch")?,
+        };
+
+        let remaining = first.total_pages.saturating_sub(first.page) + 1;
+        if remaining > max_pages {
+            Err(ErrorKind::TooManyPages(remaining, max_pages))?
+        }
+
+        Paginated::from_pages(::std::iter::once(Ok(first)).chain(pages))
+    }
+}
+
+pub struct Pages<'r, R: 'r, I: 'r> {
+    builder: &'r mut RequestBuilder<R, I>,
+    next_page: Option<u32>,
+}
+
+impl<'a, 'r, R, I> Iterator for Pages<'r, R, I>
+    where R: api::Request<'a>,
+          I: From<<R::ResponseType as api::Response>::ItemType>
+{
+    type Item = Result<Paginated<I>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page_num = match self.next_page {
+            Some(page_num) => page_num,
+            None => return None,
+        };
+
+        let result = self.builder.set_page(page_num).send();
+
+        self.next_page = match result {
+            Ok(ref page) => page.next_page(),
+            Err(_) => None,
+        };
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category_of(query: SongQuery) -> String {
+        let client = Client::default().unwrap();
+        let request = client.songs().by_query(query);
+        request.request.category_cd.to_string()
+    }
+
+    #[test]
+    fn query_is_empty() {
+        assert!(SongQuery::new().is_empty());
+        assert!(!SongQuery::new().artist_id(1).is_empty());
+        assert!(!SongQuery::new().category(category::new_songs::ALL).is_empty());
+    }
+
+    #[test]
+    fn query_fields() {
+        let client = Client::default().unwrap();
+        let query = SongQuery::new()
+            .title("Catch the Moment", MatchType::StartsWith)
+            .artist_name("LiSA", MatchType::Contains)
+            .series("ソードアート・オンライン");
+        let request = client.songs().by_query(query).request;
+
+        assert_eq!(request.song_name, Some("Catch the Moment"));
+        assert_eq!(request.song_match_type, Some("0"));
+        assert_eq!(request.artist_name, Some("LiSA"));
+        assert_eq!(request.artist_match_type, Some("1"));
+        assert_eq!(request.program_title, Some("ソードアート・オンライン"));
+        assert_eq!(request.artist_id, None);
+    }
+
+    #[test]
+    fn query_default_categories() {
+        let title = SongQuery::new().title("a", MatchType::Contains).artist_id(1);
+        let artist = SongQuery::new().artist_name("a", MatchType::Contains).series("b");
+        let series = SongQuery::new().series("b");
+
+        assert_eq!(category_of(title), category::SONG_NAME.id.0);
+        assert_eq!(category_of(artist), category::ARTIST_NAME.id.0);
+        assert_eq!(category_of(series), category::series::ANIME.id.0);
+    }
+
+    #[test]
+    fn query_category_overrides_default() {
+        let query = SongQuery::new().series("b").category(category::series::TOKUSATSU);
+        assert_eq!(category_of(query), category::series::TOKUSATSU.id.0);
+    }
 }
@@ -0,0 +1,158 @@
+use client::{Client, RequestBuilder};
+use error::*;
+use model::{Paginated, Song, SongId};
+use protocol::{api, exist};
+use std::collections::HashMap;
+
+// Which machines (by serial number) carry each song
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Availability {
+    #[serde(rename = "serialNos")]
+    pub serial_nos: Vec<String>,
+    pub songs: Vec<SongAvailability>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SongAvailability {
+    pub song: Song,
+    // One entry per serial number, in the same order as `Availability::serial_nos`
+    pub available: Vec<bool>,
+}
+
+impl Availability {
+    // Each page should be the result of the same request, sent with the serial number
+    // at the same position in `serial_nos`
+    pub fn from_pages(serial_nos: Vec<String>, pages: Vec<Paginated<Song>>) -> Self {
+        let machine_count = serial_nos.len();
+        let mut songs: Vec<SongAvailability> = Vec::new();
+        let mut positions: HashMap<SongId, usize> = HashMap::new();
+
+        for (machine_index, page) in pages.into_iter().enumerate() {
+            for song in page.items {
+                let position = *positions.entry(song.id).or_insert(songs.len());
+
+                if position == songs.len() {
+                    songs.push(SongAvailability {
+                        song: song,
+                        available: vec![false; machine_count],
+                    });
+                }
+
+                songs[position].available[machine_index] = true;
+            }
+        }
+
+        Availability {
+            serial_nos: serial_nos,
+            songs: songs,
+        }
+    }
+}
+
+// Only lookups by ID or title and artist are compared directly, since they aren't
+// paginated. Other requests go through `Client::compare_request`.
+impl<'a> RequestBuilder<exist::Request<'a>, Song> {
+    // Sends the same request once per serial number
+    pub fn compare_serial_nos(&mut self, serial_nos: &[&'a str]) -> Result<Availability> {
+        let mut pages = Vec::with_capacity(serial_nos.len());
+
+        for serial_no in serial_nos {
+            pages.push(self.set_serial_no(Some(*serial_no)).send()?);
+        }
+
+        let serial_nos = serial_nos.iter().map(|s| s.to_string()).collect();
+        Ok(Availability::from_pages(serial_nos, pages))
+    }
+}
+
+impl<'a> Client<'a> {
+    pub fn compare_ids(&self, song_ids: &[u32], serial_nos: &[&str]) -> Result<Availability> {
+        self.songs().by_ids(song_ids).compare_serial_nos(serial_nos)
+    }
+
+    // Compares the songs on the current page of a request, such as a title search. Each
+    // machine's results can be paginated differently, so a song on this page for one
+    // machine may be on another page for the next. The songs found for any machine are
+    // looked up by ID on every machine instead.
+    pub fn compare_request<'r, R>(&self,
+                                  request: &mut RequestBuilder<R, Song>,
+                                  serial_nos: &[&'r str])
+                                  -> Result<Availability>
+        where R: api::Request<'r>,
+              Song: From<<R::ResponseType as api::Response>::ItemType>
+    {
+        let mut ids = Vec::new();
+
+        for serial_no in serial_nos {
+            for song in request.set_serial_no(Some(*serial_no)).send()?.items {
+                if !ids.contains(&song.id.0) {
+                    ids.push(song.id.0);
+                }
+            }
+        }
+
+        if ids.is_empty() {
+            let serial_nos = serial_nos.iter().map(|s| s.to_string()).collect();
+            return Ok(Availability::from_pages(serial_nos, Vec::new()));
+        }
+
+        self.compare_ids(&ids, serial_nos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::{Artist, ArtistId};
+
+    fn song(id: u32) -> Song {
+        Song {
+            id: SongId(id),
+            title: format!("Song {}", id),
+            artist: Artist {
+                id: ArtistId(1),
+                name: "Artist".into(),
+                first_kana: None,
+            },
+            date_added: None,
+            end_date: None,
+            lyrics: None,
+            series: None,
+            has_video: false,
+            first_kana: None,
+        }
+    }
+
+    fn page(ids: &[u32]) -> Paginated<Song> {
+        Paginated {
+            page: 1,
+            artist_category_id: "010000".into(),
+            series_category_id: None,
+            total_items: ids.len() as u32,
+            total_pages: 1,
+            items: ids.iter().map(|&id| song(id)).collect(),
+        }
+    }
+
+    #[test]
+    fn songs_in_order_first_seen() {
+        let serial_nos = vec!["AB316238".to_string(), "CD123456".to_string()];
+        let availability = Availability::from_pages(serial_nos.clone(),
+                                                    vec![page(&[2, 1]), page(&[3, 2])]);
+
+        assert_eq!(availability.serial_nos, serial_nos);
+
+        let songs = availability.songs
+            .iter()
+            .map(|s| (s.song.id.0, s.available.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(songs,
+                   vec![(2, vec![true, true]), (1, vec![true, false]), (3, vec![false, true])]);
+    }
+
+    #[test]
+    fn no_songs() {
+        let availability = Availability::from_pages(vec!["AB316238".to_string()], vec![page(&[])]);
+        assert!(availability.songs.is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+use client::Client;
+use error::*;
+use model::{Song, SongId};
+use snapshot::Snapshot;
+use std::collections::{HashMap, HashSet};
+use util;
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ChangedSong {
+    pub song: Song,
+    #[serde(rename = "endDate", skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<Change<Option<String>>>,
+    #[serde(rename = "hasVideo", skip_serializing_if = "Option::is_none")]
+    pub has_video: Option<Change<bool>>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Diff {
+    // Set if the snapshots were taken with different serial numbers, in which case songs may
+    // be added or removed only because the machines carry different songs
+    #[serde(rename = "serialNo", skip_serializing_if = "Option::is_none")]
+    pub serial_no: Option<Change<Option<String>>>,
+    pub added: Vec<Song>,
+    // Songs missing from the new snapshot whose end date had passed when it was fetched, or
+    // that were confirmed missing by `Client::confirm_removed`
+    pub removed: Vec<Song>,
+    // Songs missing from the new snapshot that weren't due to end. They may have only
+    // rotated out of the snapshot's categories (e.g., a ranking), and still be available.
+    #[serde(rename = "leftCategories")]
+    pub left_categories: Vec<Song>,
+    pub changed: Vec<ChangedSong>,
+    // Songs from the new snapshot whose end date is coming up soon, soonest first
+    pub leaving: Vec<Song>,
+}
+
+// Each song only once, in the order they first appear
+fn unique_songs(snapshot: &Snapshot) -> Vec<&Song> {
+    let mut seen = HashSet::new();
+
+    snapshot.records
+        .iter()
+        .filter_map(|record| record.song.as_ref())
+        .filter(|song| seen.insert(song.id))
+        .collect()
+}
+
+impl Diff {
+    // Compares songs by ID. `leaving` contains songs ending within `leaving_within_days`
+    // of when the new snapshot was fetched.
+    pub fn between(old: &Snapshot, new: &Snapshot, leaving_within_days: u32) -> Self {
+        let old_songs = unique_songs(old);
+        let new_songs = unique_songs(new);
+
+        let old_by_id: HashMap<SongId, &Song> =
+            old_songs.iter().map(|song| (song.id, *song)).collect();
+        let new_by_id: HashMap<SongId, &Song> =
+            new_songs.iter().map(|song| (song.id, *song)).collect();
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+
+        for new_song in &new_songs {
+            let old_song = match old_by_id.get(&new_song.id) {
+                Some(old_song) => old_song,
+                None => {
+                    added.push((*new_song).clone());
+                    continue;
+                }
+            };
+
+            let end_date = if old_song.end_date != new_song.end_date {
+                Some(Change {
+                    old: old_song.end_date.clone(),
+                    new: new_song.end_date.clone(),
+                })
+            } else {
+                None
+            };
+
+            let has_video = if old_song.has_video != new_song.has_video {
+                Some(Change {
+                    old: old_song.has_video,
+                    new: new_song.has_video,
+                })
+            } else {
+                None
+            };
+
+            if end_date.is_some() || has_video.is_some() {
+                changed.push(ChangedSong {
+                    song: (*new_song).clone(),
+                    end_date: end_date,
+                    has_video: has_video,
+                });
+            }
+        }
+
+        let today = (new.header.fetched_at / SECONDS_PER_DAY) as i64;
+        let last_day = today + leaving_within_days as i64;
+
+        let (removed, left_categories): (Vec<_>, Vec<_>) = old_songs.iter()
+            .filter(|song| !new_by_id.contains_key(&song.id))
+            .map(|song| (*song).clone())
+            .partition(|song| {
+                song.end_date
+                    .as_ref()
+                    .and_then(|date| util::days_since_epoch(date))
+                    .map_or(false, |day| day < today)
+            });
+
+        let serial_no = if old.header.serial_no != new.header.serial_no {
+            Some(Change {
+                old: old.header.serial_no.clone(),
+                new: new.header.serial_no.clone(),
+            })
+        } else {
+            None
+        };
+
+        let mut leaving = new_songs.iter()
+            .filter_map(|song| {
+                song.end_date
+                    .as_ref()
+                    .and_then(|date| util::days_since_epoch(date))
+                    .and_then(|day| if day >= today && day <= last_day {
+                        Some((day, (*song).clone()))
+                    } else {
+                        None
+                    })
+            })
+            .collect::<Vec<_>>();
+        leaving.sort_by_key(|&(day, _)| day);
+
+        Diff {
+            serial_no: serial_no,
+            added: added,
+            removed: removed,
+            left_categories: left_categories,
+            changed: changed,
+            leaving: leaving.into_iter().map(|(_, song)| song).collect(),
+        }
+    }
+}
+
+impl<'a> Client<'a> {
+    // Looks up `left_categories` by ID, moving the songs that aren't found to `removed`.
+    // If `serial_no` is `None`, the client's default serial number is used.
+    pub fn confirm_removed(&self, diff: &mut Diff, serial_no: Option<&str>) -> Result<()> {
+        if diff.left_categories.is_empty() {
+            return Ok(());
+        }
+
+        let ids = diff.left_categories.iter().map(|song| song.id.0).collect::<Vec<_>>();
+        let mut request = self.songs().by_ids(&ids);
+        if serial_no.is_some() {
+            request.set_serial_no(serial_no);
+        }
+
+        let found = request.send()?
+            .items
+            .into_iter()
+            .map(|song| song.id)
+            .collect::<HashSet<_>>();
+
+        let (left_categories, removed): (Vec<_>, Vec<_>) = diff.left_categories
+            .drain(..)
+            .partition(|song| found.contains(&song.id));
+
+        diff.left_categories = left_categories;
+        diff.removed.extend(removed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::{Artist, ArtistId};
+
+    // 2017/03/20
+    const FETCHED_AT: u64 = 1490000000;
+
+    fn song(id: u32, end_date: Option<&str>, has_video: bool) -> Song {
+        Song {
+            id: SongId(id),
+            title: format!("Song {}", id),
+            artist: Artist {
+                id: ArtistId(1),
+                name: "Artist".into(),
+                first_kana: None,
+            },
+            date_added: None,
+            end_date: end_date.map(|date| date.to_string()),
+            lyrics: None,
+            series: None,
+            has_video: has_video,
+            first_kana: None,
+        }
+    }
+
+    fn snapshot(serial_no: Option<&str>, songs: Vec<Song>) -> Snapshot {
+        let mut snapshot = Snapshot::new(serial_no);
+        snapshot.header.fetched_at = FETCHED_AT;
+        snapshot.add_songs("030100", songs);
+        snapshot
+    }
+
+    fn ids(songs: &[Song]) -> Vec<u32> {
+        songs.iter().map(|song| song.id.0).collect()
+    }
+
+    #[test]
+    fn added_and_changed() {
+        let old = snapshot(None, vec![song(1, None, false), song(2, None, false)]);
+        let new = snapshot(None,
+                           vec![song(1, Some("2017/06/01"), true),
+                                song(2, None, false),
+                                song(3, None, false)]);
+
+        let diff = Diff::between(&old, &new, 30);
+
+        assert_eq!(ids(&diff.added), vec![3]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].song.id, SongId(1));
+        assert_eq!(diff.changed[0].end_date,
+                   Some(Change {
+                       old: None,
+                       new: Some("2017/06/01".to_string()),
+                   }));
+        assert_eq!(diff.changed[0].has_video,
+                   Some(Change {
+                       old: false,
+                       new: true,
+                   }));
+        assert!(diff.serial_no.is_none());
+    }
+
+    #[test]
+    fn removed_only_if_ended() {
+        let old = snapshot(None,
+                           vec![song(1, Some("2017/03/19"), false),
+                                song(2, Some("2017/03/20"), false),
+                                song(3, None, false)]);
+        let new = snapshot(None, vec![]);
+
+        let diff = Diff::between(&old, &new, 30);
+
+        assert_eq!(ids(&diff.removed), vec![1]);
+        assert_eq!(ids(&diff.left_categories), vec![2, 3]);
+    }
+
+    #[test]
+    fn leaving_soonest_first() {
+        let songs = vec![song(1, Some("2017/04/10"), false),
+                         song(2, Some("2017/03/25"), false),
+                         song(3, Some("2017/03/19"), false),
+                         song(4, Some("2017/04/30"), false),
+                         song(5, None, false)];
+        let old = snapshot(None, vec![]);
+        let new = snapshot(None, songs);
+
+        let diff = Diff::between(&old, &new, 30);
+
+        assert_eq!(ids(&diff.leaving), vec![2, 1]);
+    }
+
+    #[test]
+    fn serial_no_mismatch() {
+        let old = snapshot(Some("AB316238"), vec![]);
+        let new = snapshot(Some("AB123456"), vec![]);
+
+        let diff = Diff::between(&old, &new, 30);
+
+        assert_eq!(diff.serial_no,
+                   Some(Change {
+                       old: Some("AB316238".to_string()),
+                       new: Some("AB123456".to_string()),
+                   }));
+    }
+}
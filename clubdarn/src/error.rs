@@ -6,4 +6,21 @@ error_chain! {
         Http(reqwest::Error);
         Json(serde_json::Error);
     }
+
+    errors {
+        RecommendCredentialsRejected(reason: String) {
+            description("recommend API rejected credentials")
+            display("recommend API rejected credentials ({})", reason)
+        }
+
+        UnexpectedResponse(body: String) {
+            description("unexpected API response")
+            display("unexpected API response: {}", body)
+        }
+
+        TooManyPages(pages: u32, max_pages: u32) {
+            description("too many pages to fetch")
+            display("the results span {} pages, more than the limit of {}", pages, max_pages)
+        }
+    }
 }
@@ -0,0 +1,307 @@
+use error::*;
+use model::{Paginated, Song};
+use std::cmp::Ordering;
+use std::str::FromStr;
+use util;
+
+// Predicates over `Song` fields. Unset fields match every song.
+#[derive(Debug, Default)]
+pub struct SongFilter {
+    pub has_video: Option<bool>,
+    pub has_series: Option<bool>,
+    pub added_after: Option<String>,
+    pub added_before: Option<String>,
+    pub first_kana: Option<char>,
+}
+
+impl SongFilter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn has_video(mut self, has_video: bool) -> Self {
+        self.has_video = Some(has_video);
+        self
+    }
+
+    pub fn has_series(mut self, has_series: bool) -> Self {
+        self.has_series = Some(has_series);
+        self
+    }
+
+    // Dates are inclusive, and can be formatted as "2017/03/15", "2017-03-15" or "20170315"
+    pub fn added_after(mut self, date: &str) -> Result<Self> {
+        self.added_after = Some(parse_date(date)?);
+        Ok(self)
+    }
+
+    pub fn added_before(mut self, date: &str) -> Result<Self> {
+        self.added_before = Some(parse_date(date)?);
+        Ok(self)
+    }
+
+    pub fn first_kana(mut self, kana: char) -> Self {
+        self.first_kana = Some(kana);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.has_video.is_none() && self.has_series.is_none() && self.added_after.is_none() &&
+        self.added_before.is_none() && self.first_kana.is_none()
+    }
+
+    pub fn matches(&self, song: &Song) -> bool {
+        if let Some(has_video) = self.has_video {
+            if song.has_video != has_video {
+                return false;
+            }
+        }
+
+        if let Some(has_series) = self.has_series {
+            if song.series.is_some() != has_series {
+                return false;
+            }
+        }
+
+        // Dates are formatted as "YYYY/MM/DD", so they can be compared as strings
+        if let Some(ref after) = self.added_after {
+            match song.date_added {
+                Some(ref date) if date >= after => {}
+                _ => return false,
+            }
+        }
+
+        if let Some(ref before) = self.added_before {
+            match song.date_added {
+                Some(ref date) if date <= before => {}
+                _ => return false,
+            }
+        }
+
+        if self.first_kana.is_some() && song.first_kana != self.first_kana {
+            return false;
+        }
+
+        true
+    }
+
+    // Filtering a single page leaves `page` and `total_pages` as-is,
+    // so this is mostly useful on the result of `RequestBuilder::send_all`
+    pub fn apply(&self, page: &mut Paginated<Song>) {
+        page.items.retain(|song| self.matches(song));
+        page.total_items = page.items.len() as u32;
+    }
+}
+
+fn parse_date(input: &str) -> Result<String> {
+    util::normalize_date(input).ok_or_else(|| format!("invalid date {}", input).into())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Id,
+    Title,
+    Kana,
+    Artist,
+    DateAdded,
+    EndDate,
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "id" => Ok(SortKey::Id),
+            "title" => Ok(SortKey::Title),
+            "kana" => Ok(SortKey::Kana),
+            "artist" => Ok(SortKey::Artist),
+            "date" => Ok(SortKey::DateAdded),
+            "end-date" => Ok(SortKey::EndDate),
+            other => Err(format!("unknown sort key {}", other).into()),
+        }
+    }
+}
+
+pub const SORT_KEYS: [&'static str; 6] = ["id", "title", "kana", "artist", "date", "end-date"];
+
+// Songs missing the sort key are placed after the ones that have it
+fn cmp_missing_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+impl SortKey {
+    pub fn compare(&self, a: &Song, b: &Song) -> Ordering {
+        match *self {
+            SortKey::Id => a.id.0.cmp(&b.id.0),
+            SortKey::Title => a.title.cmp(&b.title),
+            SortKey::Kana => {
+                match cmp_missing_last(a.first_kana, b.first_kana) {
+                    Ordering::Equal => a.title.cmp(&b.title),
+                    other => other,
+                }
+            }
+            SortKey::Artist => a.artist.name.cmp(&b.artist.name),
+            SortKey::DateAdded => cmp_missing_last(a.date_added.as_ref(), b.date_added.as_ref()),
+            SortKey::EndDate => cmp_missing_last(a.end_date.as_ref(), b.end_date.as_ref()),
+        }
+    }
+
+    // Dates are only included in song details, so results without them (e.g.,
+    // recommendations) need to be enriched before sorting by these keys
+    pub fn needs_details(&self) -> bool {
+        match *self {
+            SortKey::DateAdded | SortKey::EndDate => true,
+            _ => false,
+        }
+    }
+}
+
+// The sort is stable, so songs that compare equal keep their original order
+pub fn sort_songs(songs: &mut [Song], key: SortKey, descending: bool) {
+    sort_songs_by(songs, key, descending, |song| song)
+}
+
+// Like `sort_songs`, for items that wrap a song (e.g., recommendations)
+pub fn sort_songs_by<T, F>(items: &mut [T], key: SortKey, descending: bool, song: F)
+    where F: Fn(&T) -> &Song
+{
+    if descending {
+        items.sort_by(|a, b| key.compare(song(b), song(a)));
+    } else {
+        items.sort_by(|a, b| key.compare(song(a), song(b)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::{Artist, ArtistId, SongId};
+
+    fn song(id: u32, title: &str, date_added: Option<&str>, first_kana: Option<char>) -> Song {
+        Song {
+            id: SongId(id),
+            title: title.to_string(),
+            artist: Artist {
+                id: ArtistId(1),
+                name: "Artist".into(),
+                first_kana: None,
+            },
+            date_added: date_added.map(|date| date.to_string()),
+            end_date: None,
+            lyrics: None,
+            series: None,
+            has_video: false,
+            first_kana: first_kana,
+        }
+    }
+
+    fn ids(songs: &[Song]) -> Vec<u32> {
+        songs.iter().map(|song| song.id.0).collect()
+    }
+
+    #[test]
+    fn unset_fields_match_everything() {
+        let filter = SongFilter::new();
+
+        assert!(filter.is_empty());
+        assert!(filter.matches(&song(1, "A", None, None)));
+    }
+
+    #[test]
+    fn video_and_series() {
+        let mut with_video = song(1, "A", None, None);
+        with_video.has_video = true;
+        let mut in_series = song(2, "B", None, None);
+        in_series.series = Some("Series".into());
+
+        let filter = SongFilter::new().has_video(true);
+        assert!(!filter.is_empty());
+        assert!(filter.matches(&with_video));
+        assert!(!filter.matches(&in_series));
+
+        let filter = SongFilter::new().has_series(false);
+        assert!(filter.matches(&with_video));
+        assert!(!filter.matches(&in_series));
+    }
+
+    #[test]
+    fn dates_are_inclusive() {
+        let filter = SongFilter::new()
+            .added_after("2017-03-01")
+            .and_then(|filter| filter.added_before("20170331"))
+            .unwrap();
+
+        assert!(filter.matches(&song(1, "A", Some("2017/03/01"), None)));
+        assert!(filter.matches(&song(2, "B", Some("2017/03/31"), None)));
+        assert!(!filter.matches(&song(3, "C", Some("2017/04/01"), None)));
+        // Songs without a date can't be compared
+        assert!(!filter.matches(&song(4, "D", None, None)));
+    }
+
+    #[test]
+    fn invalid_dates() {
+        assert!(SongFilter::new().added_after("March 2017").is_err());
+        assert!(SongFilter::new().added_before("2017/3/1").is_err());
+    }
+
+    #[test]
+    fn apply_updates_total_items() {
+        let mut page = Paginated {
+            page: 1,
+            artist_category_id: "010000".into(),
+            series_category_id: None,
+            total_items: 3,
+            total_pages: 1,
+            items: vec![song(1, "A", None, Some('あ')),
+                        song(2, "B", None, Some('か')),
+                        song(3, "C", None, Some('あ'))],
+        };
+
+        SongFilter::new().first_kana('あ').apply(&mut page);
+
+        assert_eq!(ids(&page.items), vec![1, 3]);
+        assert_eq!(page.total_items, 2);
+    }
+
+    #[test]
+    fn sort_keys() {
+        assert_eq!("end-date".parse::<SortKey>().unwrap(), SortKey::EndDate);
+        assert!("popularity".parse::<SortKey>().is_err());
+        assert!(SORT_KEYS.iter().all(|key| key.parse::<SortKey>().is_ok()));
+    }
+
+    #[test]
+    fn sort_with_missing_values_last() {
+        let mut songs = vec![song(1, "B", None, None),
+                             song(2, "A", Some("2017/03/02"), Some('か')),
+                             song(3, "C", Some("2017/03/01"), Some('あ')),
+                             song(4, "A", None, Some('あ'))];
+
+        sort_songs(&mut songs, SortKey::DateAdded, false);
+        assert_eq!(ids(&songs), vec![3, 2, 1, 4]);
+
+        // Ties are broken by title
+        sort_songs(&mut songs, SortKey::Kana, false);
+        assert_eq!(ids(&songs), vec![4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn sort_descending_and_stable() {
+        let mut songs = vec![song(3, "A", None, None),
+                             song(1, "B", None, None),
+                             song(2, "C", None, None)];
+
+        sort_songs(&mut songs, SortKey::Id, true);
+        assert_eq!(ids(&songs), vec![3, 2, 1]);
+
+        // Every song has the same artist
+        sort_songs(&mut songs, SortKey::Artist, false);
+        assert_eq!(ids(&songs), vec![3, 2, 1]);
+    }
+}
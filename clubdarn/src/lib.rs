@@ -10,14 +10,28 @@ extern crate serde_derive;
 extern crate serde;
 extern crate serde_json;
 extern crate reqwest;
+extern crate toml;
 
 mod protocol;
 mod model;
 mod util;
 mod client;
 
+pub mod artist;
+pub mod cache;
+pub mod catalogue;
 pub mod category;
+pub mod compare;
+pub mod diff;
 pub mod error;
-pub use client::{Client, Metadata, MatchType, TitleAndArtist, RequestBuilder};
+pub mod filter;
+pub mod local;
+pub mod machine;
+pub mod matching;
+pub mod ranking;
+pub mod series;
+pub mod snapshot;
+pub use client::{Client, Metadata, MatchType, RecommendConfig, TitleAndArtist, RequestBuilder,
+                 SongQuery};
 pub use error::{Error, Result};
-pub use model::{Artist, ArtistId, Song, SongId, Series, Paginated};
+pub use model::{Artist, ArtistId, Song, SongId, Series, Paginated, RecommendedSong};
@@ -0,0 +1,230 @@
+use catalogue::Catalogue;
+use category;
+use client::MatchType;
+use error::*;
+use model::{Artist, Paginated, Series, Song};
+use snapshot::Snapshot;
+use std::collections::HashSet;
+use std::io::BufRead;
+
+// Number of items per page of local results
+pub const PAGE_SIZE: usize = 100;
+
+struct Entry {
+    category: String,
+    series: Option<String>,
+    song: Song,
+}
+
+// Answers the same queries as `Client` using a snapshot, for browsing without
+// a connection. Titles and names are matched exactly as given, like the server
+// does, so offline results are the same as online ones.
+pub struct LocalIndex {
+    serial_no: Option<String>,
+    entries: Vec<Entry>,
+    artists: Vec<Artist>,
+    series: Vec<(String, Series)>,
+}
+
+fn is_match(haystack: &str, needle: &str, match_type: MatchType) -> bool {
+    match match_type {
+        MatchType::StartsWith => haystack.starts_with(needle),
+        MatchType::Contains => haystack.contains(needle),
+    }
+}
+
+// Builds a page of results in the same shape as the ones returned by `Client`
+fn paginate<T>(items: Vec<T>, category_id: &str, page: u32) -> Paginated<T> {
+    let total_items = items.len();
+    let total_pages = ((total_items + PAGE_SIZE - 1) / PAGE_SIZE) as u32;
+    let start = (page.saturating_sub(1) as usize) * PAGE_SIZE;
+
+    Paginated {
+        page: page,
+        artist_category_id: category::artist_category(category_id).id.0.to_string(),
+        series_category_id: category::series_category(category_id).map(|c| c.id.0.to_string()),
+        total_items: total_items as u32,
+        total_pages: if total_pages == 0 { 1 } else { total_pages },
+        items: items.into_iter().skip(start).take(PAGE_SIZE).collect(),
+    }
+}
+
+impl LocalIndex {
+    pub fn new(snapshot: Snapshot) -> Self {
+        let mut entries = Vec::new();
+        let mut artists = Vec::new();
+        let mut series = Vec::new();
+        let mut current_series: Option<String> = None;
+
+        for record in snapshot.records {
+            if let Some(artist) = record.artist {
+                artists.push(artist);
+            }
+
+            // Songs in series categories follow the record for their series
+            if let Some(s) = record.series {
+                current_series = Some(s.title.clone());
+                series.push((record.category.clone(), s));
+            }
+
+            if let Some(song) = record.song {
+                let is_series_category = series.last()
+                    .map_or(false, |&(ref category, _)| *category == record.category);
+
+                entries.push(Entry {
+                    series: if is_series_category {
+                        current_series.clone()
+                    } else {
+                        None
+                    },
+                    category: record.category,
+                    song: song,
+                });
+            }
+        }
+
+        LocalIndex {
+            serial_no: snapshot.header.serial_no,
+            entries: entries,
+            artists: artists,
+            series: series,
+        }
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(LocalIndex::new(Snapshot::read(reader)?))
+    }
+
+    // The serial number the snapshot was fetched with, if any
+    pub fn serial_no(&self) -> Option<&str> {
+        self.serial_no.as_ref().map(|s| s.as_str())
+    }
+
+    // Songs matching the predicate, each only once
+    fn songs<P>(&self, predicate: P) -> Vec<Song>
+        where P: Fn(&Entry) -> bool
+    {
+        let mut seen = HashSet::new();
+
+        self.entries
+            .iter()
+            .filter(|entry| predicate(entry) && seen.insert(entry.song.id))
+            .map(|entry| entry.song.clone())
+            .collect()
+    }
+}
+
+impl Catalogue for LocalIndex {
+    fn songs_by_title(&self,
+                      title: &str,
+                      match_type: MatchType,
+                      page: u32)
+                      -> Result<Paginated<Song>> {
+        let songs = self.songs(|entry| is_match(&entry.song.title, title, match_type));
+        Ok(paginate(songs, category::SONG_NAME.id.0, page))
+    }
+
+    fn songs_by_artist_in_category_id(&self,
+                                      artist_id: u32,
+                                      category_id: &str,
+                                      page: u32)
+                                      -> Result<Paginated<Song>> {
+        // Any category will do for regular artist searches,
+        // but live performances only come from their own category
+        let any_category = category_id == category::ARTIST_NAME.id.0;
+
+        let songs = self.songs(|entry| {
+            entry.song.artist.id.0 == artist_id && (any_category || entry.category == category_id)
+        });
+        Ok(paginate(songs, category_id, page))
+    }
+
+    fn songs_by_series_in_category_id(&self,
+                                      title: &str,
+                                      category_id: &str,
+                                      page: u32)
+                                      -> Result<Paginated<Song>> {
+        // Like the server, series are searched by their full title
+        let songs = self.songs(|entry| {
+            entry.category == category_id &&
+            entry.series.as_ref().map_or(false, |s| s == title)
+        });
+        Ok(paginate(songs, category_id, page))
+    }
+
+    fn songs_by_category_id(&self, category_id: &str, page: u32) -> Result<Paginated<Song>> {
+        let songs = self.songs(|entry| entry.category == category_id);
+        Ok(paginate(songs, category_id, page))
+    }
+
+    fn artists_by_name(&self,
+                       name: &str,
+                       match_type: MatchType,
+                       page: u32)
+                       -> Result<Paginated<Artist>> {
+        let artists = self.artists
+            .iter()
+            .filter(|artist| is_match(&artist.name, name, match_type))
+            .cloned()
+            .collect();
+        Ok(paginate(artists, category::ARTIST_NAME.id.0, page))
+    }
+
+    fn series_by_category_id(&self, category_id: &str, page: u32) -> Result<Paginated<Series>> {
+        let mut seen = HashSet::new();
+
+        let series = self.series
+            .iter()
+            .filter(|&&(ref category, ref s)| {
+                category == category_id && seen.insert(s.title.clone())
+            })
+            .map(|&(_, ref s)| s.clone())
+            .collect();
+        Ok(paginate(series, category_id, page))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(count: u32) -> Vec<u32> {
+        (1..count + 1).collect()
+    }
+
+    #[test]
+    fn pages_of_page_size() {
+        let page = paginate(items(250), category::SONG_NAME.id.0, 1);
+        assert_eq!(page.items, items(100));
+        assert_eq!(page.total_items, 250);
+        assert_eq!(page.total_pages, 3);
+
+        let page = paginate(items(250), category::SONG_NAME.id.0, 3);
+        assert_eq!(page.page, 3);
+        assert_eq!(page.items.first(), Some(&201));
+        assert_eq!(page.items.len(), 50);
+    }
+
+    #[test]
+    fn pages_past_the_end_are_empty() {
+        let page = paginate(items(100), category::SONG_NAME.id.0, 2);
+        assert!(page.items.is_empty());
+        assert_eq!(page.total_pages, 1);
+    }
+
+    #[test]
+    fn no_items_is_one_empty_page() {
+        let page = paginate(Vec::<u32>::new(), category::SONG_NAME.id.0, 1);
+        assert!(page.items.is_empty());
+        assert_eq!(page.total_items, 0);
+        assert_eq!(page.total_pages, 1);
+    }
+
+    #[test]
+    fn exact_matches() {
+        assert!(is_match("Hello World", "Hello", MatchType::StartsWith));
+        assert!(!is_match("Hello World", "World", MatchType::StartsWith));
+        assert!(is_match("Hello World", "World", MatchType::Contains));
+        assert!(!is_match("Hello World", "world", MatchType::Contains));
+    }
+}
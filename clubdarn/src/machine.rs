@@ -0,0 +1,212 @@
+use error::*;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use toml;
+
+// Karaoke machine model families. Availability of songs differs between models.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    LiveDamStadiumStage,
+    LiveDamStadium,
+    LiveDam,
+    PremierDam,
+    CyberDam,
+}
+
+pub const MODELS: [Model; 5] = [Model::LiveDamStadiumStage,
+                                Model::LiveDamStadium,
+                                Model::LiveDam,
+                                Model::PremierDam,
+                                Model::CyberDam];
+
+impl Model {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Model::LiveDamStadiumStage => "LIVE DAM STADIUM STAGE",
+            Model::LiveDamStadium => "LIVE DAM STADIUM",
+            Model::LiveDam => "LIVE DAM",
+            Model::PremierDam => "Premier DAM",
+            Model::CyberDam => "Cyber DAM",
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Accepts names regardless of case and spacing (e.g., "LiveDAM STADIUM")
+impl FromStr for Model {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let normalize = |name: &str| {
+            name.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase()
+        };
+        let input = normalize(s);
+
+        MODELS.iter()
+            .find(|model| normalize(model.name()) == input)
+            .cloned()
+            .ok_or_else(|| format!("unknown machine model {}", s).into())
+    }
+}
+
+impl Serialize for Model {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Machine {
+    #[serde(rename = "serialNo")]
+    pub serial_no: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<Model>,
+}
+
+impl Machine {
+    pub fn new(serial_no: &str) -> Result<Self> {
+        Ok(Machine {
+            serial_no: parse_serial_no(serial_no)?,
+            model: None,
+        })
+    }
+
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+}
+
+// The format of serial numbers isn't documented (e.g., "AB316238"), so anything
+// without whitespace is passed along as-is
+pub fn parse_serial_no(input: &str) -> Result<String> {
+    let serial_no = input.trim();
+
+    if serial_no.is_empty() || serial_no.chars().any(char::is_whitespace) {
+        Err(format!("invalid serial number {}", input).into())
+    } else {
+        Ok(serial_no.to_string())
+    }
+}
+
+// Named machines, e.g., "home" for a LIVE DAM STADIUM with a known serial number
+#[derive(Clone, Debug, Default)]
+pub struct MachineProfiles {
+    profiles: BTreeMap<String, Machine>,
+}
+
+impl MachineProfiles {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Expects a TOML table per profile, named after the profile, e.g.:
+    //
+    //     [home]
+    //     serial-no = "AB316238"
+    //     model = "LIVE DAM STADIUM"
+    pub fn from_toml(input: &str) -> Result<Self> {
+        let mut parser = toml::Parser::new(input);
+
+        match parser.parse() {
+            Some(table) => Self::from_table(table),
+            None => {
+                let errors = parser.errors
+                    .iter()
+                    .map(|e| format!("line {}: {}", parser.to_linecol(e.lo).0 + 1, e.desc))
+                    .collect::<Vec<_>>();
+                Err(format!("invalid machine profiles ({})", errors.join(", ")).into())
+            }
+        }
+    }
+
+    // Like `from_toml`, for profiles that are part of a larger document (e.g., a config file)
+    pub fn from_table(table: toml::Table) -> Result<Self> {
+        let mut profiles = MachineProfiles::new();
+
+        for (name, value) in table {
+            let serial_no = value.lookup("serial-no").and_then(|v| v.as_str());
+            let model = value.lookup("model").and_then(|v| v.as_str());
+
+            let mut machine = match serial_no {
+                Some(serial_no) => {
+                    Machine::new(serial_no)
+                        .chain_err(|| format!("invalid machine profile {}", name))?
+                }
+                None => Err(format!("machine profile {} has no serial-no", name))?,
+            };
+
+            if let Some(model) = model {
+                machine = machine.with_model(model.parse()?);
+            }
+
+            profiles.insert(name, machine);
+        }
+
+        Ok(profiles)
+    }
+
+    pub fn insert(&mut self, name: String, machine: Machine) {
+        self.profiles.insert(name, machine);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Machine> {
+        self.profiles.get(name)
+    }
+
+    pub fn iter(&self) -> ::std::collections::btree_map::Iter<String, Machine> {
+        self.profiles.iter()
+    }
+
+    // Looks up a profile by name, falling back to treating the input as a serial number
+    pub fn resolve(&self, name_or_serial_no: &str) -> Result<Machine> {
+        match self.get(name_or_serial_no) {
+            Some(machine) => Ok(machine.clone()),
+            None => {
+                Machine::new(name_or_serial_no).chain_err(|| {
+                    format!("no machine profile or serial number {}", name_or_serial_no)
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serial_numbers() {
+        assert_eq!(parse_serial_no(" AB316238 ").unwrap(), "AB316238");
+        assert!(parse_serial_no("").is_err());
+        assert!(parse_serial_no("AB 316238").is_err());
+    }
+
+    #[test]
+    fn profiles_from_toml() {
+        let input = "[home]\nserial-no = \"AB316238\"\nmodel = \"LiveDAM STADIUM\"";
+        let profiles = MachineProfiles::from_toml(input).unwrap();
+
+        assert_eq!(profiles.get("home"),
+                   Some(&Machine::new("AB316238").unwrap().with_model(Model::LiveDamStadium)));
+        assert!(MachineProfiles::from_toml("[home]\nmodel = \"LIVE DAM\"").is_err());
+        assert!(MachineProfiles::from_toml("[home").is_err());
+    }
+
+    #[test]
+    fn resolve_names_then_serial_numbers() {
+        let profiles = MachineProfiles::from_toml("[home]\nserial-no = \"AB316238\"").unwrap();
+
+        assert_eq!(profiles.resolve("home").unwrap().serial_no, "AB316238");
+        assert_eq!(profiles.resolve("CD123456").unwrap().serial_no, "CD123456");
+    }
+}
@@ -0,0 +1,317 @@
+use client::{Client, MatchType, TitleAndArtist};
+use error::*;
+use model::Song;
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use util;
+
+// Fuzzy matches less similar than this are ignored
+pub const MIN_CONFIDENCE: f64 = 0.6;
+
+// How a title and artist were matched to a song
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchMethod {
+    // Found by the exist API
+    Exact,
+    // Found by searching for the title, with the same title and artist after normalizing
+    Normalized,
+    // The most similar song found by searching for the title
+    Fuzzy,
+}
+
+impl MatchMethod {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MatchMethod::Exact => "exact",
+            MatchMethod::Normalized => "normalized",
+            MatchMethod::Fuzzy => "fuzzy",
+        }
+    }
+}
+
+impl fmt::Display for MatchMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Serialize for MatchMethod {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Match {
+    pub song: Song,
+    pub method: MatchMethod,
+    // From 0 to 1. Exact and normalized matches have a confidence of 1.
+    pub confidence: f64,
+}
+
+// Whether a song has the given title and artist, ignoring case, whitespace,
+// and differences between full-width and half-width characters
+pub fn is_match(query: &TitleAndArtist, song: &Song) -> bool {
+    util::normalize(&query.title) == util::normalize(&song.title) &&
+    util::normalize(&query.artist) == util::normalize(&song.artist.name)
+}
+
+fn bigrams(input: &str) -> Vec<(char, char)> {
+    let chars = util::normalize(input).chars().collect::<Vec<_>>();
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+// Dice's coefficient of the normalized strings' character pairs, from 0 to 1
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a_bigrams = bigrams(a);
+    let mut b_bigrams = bigrams(b);
+
+    if a_bigrams.is_empty() || b_bigrams.is_empty() {
+        return if util::normalize(a) == util::normalize(b) { 1.0 } else { 0.0 };
+    }
+
+    let total = a_bigrams.len() + b_bigrams.len();
+    let mut common = 0;
+
+    for bigram in &a_bigrams {
+        if let Some(index) = b_bigrams.iter().position(|b| b == bigram) {
+            b_bigrams.swap_remove(index);
+            common += 1;
+        }
+    }
+
+    2.0 * common as f64 / total as f64
+}
+
+// The average similarity of the titles and artists, rounded to two decimal places
+fn confidence(query: &TitleAndArtist, song: &Song) -> f64 {
+    let score = (similarity(&query.title, &song.title) +
+                 similarity(&query.artist, &song.artist.name)) / 2.0;
+
+    (score * 100.0).round() / 100.0
+}
+
+// Removes bracketed parts of titles, e.g., "(TV size)" or "[Remastered]"
+fn strip_brackets(title: &str) -> String {
+    let mut depth = 0;
+    let stripped = title.chars()
+        .filter(|&c| {
+            match c {
+                '(' | '[' | '（' | '【' => depth += 1,
+                ')' | ']' | '）' | '】' if depth > 0 => {
+                    depth -= 1;
+                    return false;
+                }
+                _ => {}
+            }
+            depth == 0
+        })
+        .collect::<String>();
+
+    stripped.trim().to_string()
+}
+
+// A song with the same title and artist after normalizing, otherwise the most similar
+// song with a confidence of at least `MIN_CONFIDENCE`
+fn best_match(query: &TitleAndArtist, mut candidates: Vec<Song>) -> Option<Match> {
+    if let Some(index) = candidates.iter().position(|song| is_match(query, song)) {
+        return Some(Match {
+            song: candidates.swap_remove(index),
+            method: MatchMethod::Normalized,
+            confidence: 1.0,
+        });
+    }
+
+    let best = candidates.into_iter()
+        .map(|song| (confidence(query, &song), song))
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+    best.and_then(|(confidence, song)| if confidence >= MIN_CONFIDENCE {
+        Some(Match {
+            song: song,
+            method: MatchMethod::Fuzzy,
+            confidence: confidence,
+        })
+    } else {
+        None
+    })
+}
+
+impl<'a> Client<'a> {
+    // Finds the song for each title and artist (e.g., from a playlist), in the same order.
+    // The exist API leaves out titles it can't find, so its results are matched back to
+    // the queries by title and artist. If `serial_no` is `None`, the client's default
+    // serial number is used.
+    pub fn find_titles_and_artists(&self,
+                                   queries: &[TitleAndArtist],
+                                   serial_no: Option<&str>)
+                                   -> Result<Vec<Option<Song>>> {
+        if queries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut request = self.songs().by_titles_and_artists(queries);
+        if serial_no.is_some() {
+            request.set_serial_no(serial_no);
+        }
+
+        let songs = request.send()?.items;
+
+        let found = queries.iter()
+            .map(|query| songs.iter().find(|song| is_match(query, song)).cloned())
+            .collect();
+
+        Ok(found)
+    }
+
+    // Like `find_titles_and_artists`, but titles that the exist API can't find are
+    // searched for instead (see `search_title_and_artist`)
+    pub fn match_titles_and_artists(&self,
+                                    queries: &[TitleAndArtist],
+                                    serial_no: Option<&str>)
+                                    -> Result<Vec<Option<Match>>> {
+        let found = self.find_titles_and_artists(queries, serial_no)?;
+
+        queries.iter()
+            .zip(found)
+            .map(|(query, song)| match song {
+                Some(song) => {
+                    Ok(Some(Match {
+                        song: song,
+                        method: MatchMethod::Exact,
+                        confidence: 1.0,
+                    }))
+                }
+                None => self.search_title_and_artist(query, serial_no),
+            })
+            .collect()
+    }
+
+    // Searches for the title (without any bracketed parts, if that finds nothing),
+    // and picks the best of the songs found (see `best_match`)
+    pub fn search_title_and_artist(&self,
+                                   query: &TitleAndArtist,
+                                   serial_no: Option<&str>)
+                                   -> Result<Option<Match>> {
+        let mut candidates = self.search_title(&query.title, serial_no)?;
+
+        let stripped = strip_brackets(&query.title);
+        if candidates.is_empty() && !stripped.is_empty() && stripped != query.title {
+            candidates = self.search_title(&stripped, serial_no)?;
+        }
+
+        Ok(best_match(query, candidates))
+    }
+
+    fn search_title(&self, title: &str, serial_no: Option<&str>) -> Result<Vec<Song>> {
+        let mut request = self.songs().by_title(title, MatchType::Contains);
+        if serial_no.is_some() {
+            request.set_serial_no(serial_no);
+        }
+
+        Ok(request.send()?.items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::{Artist, ArtistId, SongId};
+
+    fn song(id: u32, title: &str, artist: &str) -> Song {
+        Song {
+            id: SongId(id),
+            title: title.to_string(),
+            artist: Artist {
+                id: ArtistId(1),
+                name: artist.to_string(),
+                first_kana: None,
+            },
+            date_added: None,
+            end_date: None,
+            lyrics: None,
+            series: None,
+            has_video: false,
+            first_kana: None,
+        }
+    }
+
+    fn query<'a>(title: &'a str, artist: &'a str) -> TitleAndArtist<'a> {
+        TitleAndArtist {
+            title: title.into(),
+            artist: artist.into(),
+        }
+    }
+
+    #[test]
+    fn similarity_of_bigrams() {
+        assert_eq!(similarity("Hello World", "hello world"), 1.0);
+        assert_eq!(similarity("ＡＢＣ", "abc"), 1.0);
+        // Only "ht" is shared out of 4 pairs each
+        assert_eq!(similarity("night", "nacht"), 0.25);
+        // Repeated pairs are only matched once
+        assert_eq!(similarity("aaaa", "aa"), 0.5);
+    }
+
+    #[test]
+    fn similarity_of_single_characters() {
+        assert_eq!(similarity("a", "A"), 1.0);
+        assert_eq!(similarity("a", "b"), 0.0);
+        assert_eq!(similarity("", "ab"), 0.0);
+    }
+
+    #[test]
+    fn strips_brackets() {
+        assert_eq!(strip_brackets("Song (TV size)"), "Song");
+        assert_eq!(strip_brackets("[Remastered] Song"), "Song");
+        assert_eq!(strip_brackets("曲名（TVサイズ）"), "曲名");
+    }
+
+    #[test]
+    fn strips_nested_and_unbalanced_brackets() {
+        assert_eq!(strip_brackets("Song (Live [2017]) Edit"), "Song  Edit");
+        assert_eq!(strip_brackets("Song (unfinished"), "Song");
+        assert_eq!(strip_brackets("Song) B"), "Song) B");
+    }
+
+    #[test]
+    fn normalized_match_before_fuzzy() {
+        let candidates = vec![song(1, "Hello World!", "Artist"),
+                              song(2, "ＨＥＬＬＯ ＷＯＲＬＤ", "artist")];
+
+        let found = best_match(&query("Hello World", "Artist"), candidates).unwrap();
+
+        assert_eq!(found.song.id, SongId(2));
+        assert_eq!(found.method, MatchMethod::Normalized);
+        assert_eq!(found.confidence, 1.0);
+    }
+
+    #[test]
+    fn most_similar_fuzzy_match() {
+        let candidates = vec![song(1, "Goodbye", "Someone"), song(2, "Hello World!", "Artist")];
+
+        let found = best_match(&query("Hello World", "Artist"), candidates).unwrap();
+
+        assert_eq!(found.song.id, SongId(2));
+        assert_eq!(found.method, MatchMethod::Fuzzy);
+        assert_eq!(found.confidence, 0.97);
+    }
+
+    #[test]
+    fn fuzzy_matches_need_min_confidence() {
+        // The same title by a different artist only has a confidence of 0.5
+        let q = query("night", "Artist");
+        assert!(best_match(&q, vec![song(1, "night", "Other")]).is_none());
+
+        // A somewhat similar artist brings it to 0.63
+        let q = query("night", "nacht");
+        let found = best_match(&q, vec![song(1, "night", "night")]).unwrap();
+        assert!(found.confidence >= MIN_CONFIDENCE);
+        assert_eq!(found.confidence, 0.63);
+
+        assert!(best_match(&q, vec![]).is_none());
+    }
+}
@@ -1,14 +1,16 @@
+use error::*;
 use protocol::{exist, recommend, search};
 use std::borrow::Cow;
 use std::convert::From;
+use std::fmt;
 use std::ops::Not;
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SongId(pub u32);
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ArtistId(pub u32);
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Artist {
     pub id: ArtistId,
     pub name: String,
@@ -16,7 +18,7 @@ pub struct Artist {
     pub first_kana: Option<char>,
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Song {
     pub id: SongId,
     pub title: String,
@@ -29,19 +31,35 @@ pub struct Song {
     pub lyrics: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub series: Option<String>,
-    #[serde(rename = "hasVideo", skip_serializing_if = "Not::not")]
+    #[serde(rename = "hasVideo", default, skip_serializing_if = "Not::not")]
     pub has_video: bool,
     #[serde(rename = "firstKana", skip_serializing_if = "Option::is_none")]
     pub first_kana: Option<char>,
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Series {
     pub title: String,
     #[serde(rename = "firstKana", skip_serializing_if = "Option::is_none")]
     pub first_kana: Option<char>,
 }
 
+// A song returned by the recommend API, along with the fields that don't fit in `Song`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RecommendedSong {
+    pub song: Song,
+    #[serde(rename = "contentsId")]
+    pub contents_id: String,
+    #[serde(rename = "artistCode")]
+    pub artist_code: String,
+    #[serde(rename = "denmokuArtist")]
+    pub denmoku_artist: String,
+    #[serde(rename = "titleYomi")]
+    pub title_yomi: String,
+    #[serde(rename = "artistYomi")]
+    pub artist_yomi: String,
+}
+
 #[derive(Debug, PartialEq, Serialize)]
 pub struct Paginated<T> {
     pub page: u32,
@@ -72,6 +90,28 @@ impl<T> Paginated<T> {
             None
         }
     }
+
+    // Merges pages into a single page containing all of their items
+    pub fn from_pages<P>(pages: P) -> Result<Self>
+        where P: IntoIterator<Item = Result<Paginated<T>>>
+    {
+        let mut pages = pages.into_iter();
+
+        let mut merged = match pages.next() {
+            Some(page) => page?,
+            None => Err("no pages to merge")?,
+        };
+
+        for page in pages {
+            merged.items.extend(page?.items);
+        }
+
+        merged.page = 1;
+        merged.total_pages = 1;
+        merged.total_items = merged.items.len() as u32;
+
+        Ok(merged)
+    }
 }
 
 impl<'a, T> From<T> for SongId
@@ -83,6 +123,20 @@ impl<'a, T> From<T> for SongId
     }
 }
 
+// Displayed the same way as in Denmoku, e.g., 360715 as "3607-15".
+// The recommend API also requires song IDs in this format.
+impl fmt::Display for SongId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let id = self.0.to_string();
+
+        if id.len() > 4 {
+            write!(f, "{}-{}", &id[..4], &id[4..])
+        } else {
+            f.write_str(&id)
+        }
+    }
+}
+
 impl<'a, T> From<T> for ArtistId
     where T: Into<Cow<'a, str>>
 {
@@ -192,6 +246,25 @@ impl From<recommend::Item> for Song {
     }
 }
 
+impl From<recommend::Item> for RecommendedSong {
+    fn from(res: recommend::Item) -> Self {
+        let contents_id = res.contents_id.clone();
+        let artist_code = res.artist_code.clone();
+        let denmoku_artist = res.denmoku_artist.clone();
+        let title_yomi = res.d_song_name_yomi.clone();
+        let artist_yomi = res.d_artist_name_yomi.clone();
+
+        RecommendedSong {
+            song: res.into(),
+            contents_id: contents_id,
+            artist_code: artist_code,
+            denmoku_artist: denmoku_artist,
+            title_yomi: title_yomi,
+            artist_yomi: artist_yomi,
+        }
+    }
+}
+
 impl From<search::Item> for Series {
     fn from(res: search::Item) -> Self {
         Series {
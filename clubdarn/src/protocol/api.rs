@@ -1,5 +1,8 @@
 use client;
+use error::*;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 pub enum RequestType {
     Json,
@@ -14,12 +17,25 @@ pub trait Request<'a>: Serialize {
     fn url() -> &'a str;
     fn from_client_metadata(meta: &client::Metadata<'a>) -> Self;
 
+    // Allows APIs to turn HTTP status codes into more specific errors
+    #[allow(unused_variables)]
+    fn check_status(status: &StatusCode) -> Result<()> {
+        Ok(())
+    }
+
+    // Allows APIs to turn error bodies into more specific errors, before the body is
+    // parsed as `ResponseType`
+    #[allow(unused_variables)]
+    fn check_body(body: &Value) -> Result<()> {
+        Ok(())
+    }
+
     fn set_serial_no(&mut self, serial_no: Option<&'a str>) -> &mut Self;
 
     fn page(&self) -> u32;
     fn set_page(&mut self, page_num: u32) -> &mut Self;
 
-    fn category(&'a self) -> Option<&'a str>;
+    fn category(&self) -> Option<&str>;
 }
 
 pub trait Response: Deserialize {
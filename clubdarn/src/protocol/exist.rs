@@ -70,7 +70,7 @@ impl<'a> api::Request<'a> for Request<'a> {
         }
     }
 
-    fn category(&'a self) -> Option<&'a str> {
+    fn category(&self) -> Option<&str> {
         None
     }
 
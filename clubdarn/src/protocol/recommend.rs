@@ -1,6 +1,9 @@
 use super::*;
 
 use client;
+use error::*;
+use reqwest::StatusCode;
+use serde_json::Value;
 use std::borrow::Cow;
 
 pub const API_URL: &'static str = "https://csgw.clubdam.com/minsei/recommend/GetRecommendSongs.api";
@@ -42,12 +45,37 @@ impl<'a> api::Request<'a> for Request<'a> {
         }
     }
 
+    // The recommend API's error responses aren't documented, so both forms are checked:
+    // a 401 or 403 status, and a 200 response whose body has no `list` of songs (which
+    // would otherwise fail to parse). Such a body is only taken as rejecting the
+    // credentials if it mentions them, and is included in the error either way, since
+    // it's the only explanation the API gives.
+    fn check_status(status: &StatusCode) -> Result<()> {
+        match *status {
+            StatusCode::Unauthorized |
+            StatusCode::Forbidden => {
+                Err(ErrorKind::RecommendCredentialsRejected(status.to_string()).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_body(body: &Value) -> Result<()> {
+        match body.as_object() {
+            Some(object) if object.contains_key("list") => Ok(()),
+            _ if mentions_auth(body) => {
+                Err(ErrorKind::RecommendCredentialsRejected(body.to_string()).into())
+            }
+            _ => Err(ErrorKind::UnexpectedResponse(body.to_string()).into()),
+        }
+    }
+
     fn set_serial_no(&mut self, serial_no: Option<&'a str>) -> &mut Self {
         self.serial = serial_no;
         self
     }
 
-    fn category(&'a self) -> Option<&'a str> {
+    fn category(&self) -> Option<&str> {
         None
     }
 
@@ -61,6 +89,30 @@ impl<'a> api::Request<'a> for Request<'a> {
     }
 }
 
+impl<'a> Request<'a> {
+    pub fn set_config(&mut self, config: &'a client::RecommendConfig) -> &mut Self {
+        self.comp_id = config.comp_id;
+        self.contract_id = config.contract_id;
+        self.comp_auth_key = &config.comp_auth_key;
+        self.format = &config.format;
+        self
+    }
+}
+
+// Whether any key or string in the body mentions authentication (e.g., `compAuthKey`)
+fn mentions_auth(value: &Value) -> bool {
+    let is_auth = |s: &str| s.to_lowercase().contains("auth");
+
+    match *value {
+        Value::String(ref s) => is_auth(s),
+        Value::Array(ref values) => values.iter().any(mentions_auth),
+        Value::Object(ref object) => {
+            object.iter().any(|(key, value)| is_auth(key) || mentions_auth(value))
+        }
+        _ => false,
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Response {
     pub list: Vec<Item>,
@@ -109,3 +161,38 @@ pub struct Item {
     #[serde(rename = "requestNo")]
     pub request_no: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::api::Request as ApiRequest;
+    use serde_json;
+
+    fn check(json: &str) -> Result<()> {
+        Request::check_body(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn songs() {
+        assert!(check(r#"{"list": []}"#).is_ok());
+    }
+
+    #[test]
+    fn rejected_credentials() {
+        let error = check(r#"{"result": "NG", "message": "Invalid compAuthKey"}"#).unwrap_err();
+        match *error.kind() {
+            ErrorKind::RecommendCredentialsRejected(_) => {}
+            ref other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn other_errors() {
+        let error = check(r#"{"result": "NG", "message": "requestNoList is required"}"#)
+            .unwrap_err();
+        match *error.kind() {
+            ErrorKind::UnexpectedResponse(ref body) => assert!(body.contains("requestNoList")),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
@@ -66,7 +66,7 @@ impl<'a> api::Request<'a> for Request<'a> {
         self
     }
 
-    fn category(&'a self) -> Option<&'a str> {
+    fn category(&self) -> Option<&str> {
         Some(self.category_cd)
     }
 
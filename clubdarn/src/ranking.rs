@@ -0,0 +1,222 @@
+// Ranking history is stored as JSON lines, one line per fetched ranking,
+// with songs listed in rank order (the first song is ranked 1st):
+//
+//     {"category":"070100","serialNo":"AB316238","fetchedAt":1490000000,
+//      "songs":[{"id":360715,...},...]}
+//
+// Appending a line per fetch (e.g., from a cron job) builds up the history.
+
+use client::Client;
+use error::*;
+use model::{Song, SongId};
+use serde::{Serialize, Serializer};
+use serde_json;
+use snapshot;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ranking {
+    pub category: String,
+    // Missing from history recorded before serial numbers were kept
+    #[serde(rename = "serialNo", default)]
+    pub serial_no: Option<String>,
+    #[serde(rename = "fetchedAt")]
+    pub fetched_at: u64,
+    pub songs: Vec<Song>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Movement {
+    New,
+    Up(u32),
+    Down(u32),
+    Unchanged,
+}
+
+#[derive(Serialize)]
+struct SerializedMovement {
+    direction: &'static str,
+    places: u32,
+}
+
+// Serialized as e.g. {"direction":"up","places":3}, where the direction is "new", "up",
+// "down" or "unchanged", and places is 0 for new and unchanged songs
+impl Serialize for Movement {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let (direction, places) = match *self {
+            Movement::New => ("new", 0),
+            Movement::Up(n) => ("up", n),
+            Movement::Down(n) => ("down", n),
+            Movement::Unchanged => ("unchanged", 0),
+        };
+
+        SerializedMovement {
+                direction: direction,
+                places: places,
+            }
+            .serialize(serializer)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RankedSong {
+    pub rank: u32,
+    #[serde(rename = "previousRank")]
+    pub previous_rank: Option<u32>,
+    pub movement: Movement,
+    pub song: Song,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DroppedSong {
+    #[serde(rename = "previousRank")]
+    pub previous_rank: u32,
+    pub song: Song,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Movements {
+    pub category: String,
+    #[serde(rename = "serialNo")]
+    pub serial_no: Option<String>,
+    #[serde(rename = "fetchedAt")]
+    pub fetched_at: u64,
+    #[serde(rename = "previousFetchedAt")]
+    pub previous_fetched_at: Option<u64>,
+    pub songs: Vec<RankedSong>,
+    // Songs from the previous ranking that are no longer ranked
+    pub dropped: Vec<DroppedSong>,
+}
+
+impl Movements {
+    // If there's no previous ranking, every song is a new entry
+    pub fn between(previous: Option<&Ranking>, current: &Ranking) -> Self {
+        let previous_ranks: HashMap<SongId, u32> = previous.map_or(HashMap::new(), |ranking| {
+            ranking.songs
+                .iter()
+                .enumerate()
+                .map(|(index, song)| (song.id, index as u32 + 1))
+                .collect()
+        });
+
+        let songs = current.songs
+            .iter()
+            .enumerate()
+            .map(|(index, song)| {
+                let rank = index as u32 + 1;
+                let previous_rank = previous_ranks.get(&song.id).cloned();
+
+                let movement = match previous_rank {
+                    None => Movement::New,
+                    Some(previous) if previous > rank => Movement::Up(previous - rank),
+                    Some(previous) if previous < rank => Movement::Down(rank - previous),
+                    Some(_) => Movement::Unchanged,
+                };
+
+                RankedSong {
+                    rank: rank,
+                    previous_rank: previous_rank,
+                    movement: movement,
+                    song: song.clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let current_ids = current.songs.iter().map(|song| song.id).collect::<HashSet<_>>();
+
+        let dropped = previous.map_or(vec![], |ranking| {
+            ranking.songs
+                .iter()
+                .enumerate()
+                .filter(|&(_, song)| !current_ids.contains(&song.id))
+                .map(|(index, song)| {
+                    DroppedSong {
+                        previous_rank: index as u32 + 1,
+                        song: song.clone(),
+                    }
+                })
+                .collect()
+        });
+
+        Movements {
+            category: current.category.clone(),
+            serial_no: current.serial_no.clone(),
+            fetched_at: current.fetched_at,
+            previous_fetched_at: previous.map(|ranking| ranking.fetched_at),
+            songs: songs,
+            dropped: dropped,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct History {
+    pub rankings: Vec<Ranking>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        let mut rankings = Vec::new();
+
+        for line in reader.lines() {
+            let line = line.chain_err(|| "failed to read ranking history")?;
+            if !line.trim().is_empty() {
+                rankings.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(History { rankings: rankings })
+    }
+
+    // Writes a single line, to be appended to an existing history file
+    pub fn append<W: Write>(mut writer: W, ranking: &Ranking) -> Result<()> {
+        serde_json::to_writer(&mut writer, ranking)?;
+        writer.write_all(b"\n").chain_err(|| "failed to write ranking history")
+    }
+
+    // Rankings for the category, oldest first
+    pub fn category(&self, category_id: &str) -> Vec<&Ranking> {
+        let mut rankings = self.rankings
+            .iter()
+            .filter(|ranking| ranking.category == category_id)
+            .collect::<Vec<_>>();
+        rankings.sort_by_key(|ranking| ranking.fetched_at);
+        rankings
+    }
+
+    // Movements between the two most recent rankings for the category
+    pub fn movements(&self, category_id: &str) -> Option<Movements> {
+        let rankings = self.category(category_id);
+
+        match rankings.len() {
+            0 => None,
+            1 => Some(Movements::between(None, rankings[0])),
+            n => Some(Movements::between(Some(rankings[n - 2]), rankings[n - 1])),
+        }
+    }
+}
+
+impl<'a> Client<'a> {
+    // Fetches every page of a ranking category.
+    // If `serial_no` is `None`, the client's default serial number is used.
+    pub fn ranking(&self, category_id: &str, serial_no: Option<&str>) -> Result<Ranking> {
+        let mut request = self.songs().by_category_id(category_id);
+        if serial_no.is_some() {
+            request.set_serial_no(serial_no);
+        }
+
+        Ok(Ranking {
+            category: category_id.to_string(),
+            serial_no: serial_no.or(self.default_serial_no()).map(String::from),
+            fetched_at: snapshot::now(),
+            songs: request.send_all()?.items,
+        })
+    }
+}
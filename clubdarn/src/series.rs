@@ -0,0 +1,94 @@
+use category::{Category, SeriesCategory};
+use client::Client;
+use error::*;
+use model::{Series, Song};
+use std::vec;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SeriesSongs {
+    pub series: Series,
+    pub songs: Vec<Song>,
+}
+
+// Walks every series in a category, fetching all pages of each series' songs
+// one series at a time
+pub struct SeriesCrawler<'c, 'a: 'c> {
+    client: &'c Client<'a>,
+    category_id: &'static str,
+    serial_no: Option<String>,
+    series: vec::IntoIter<Series>,
+    total: usize,
+    done: usize,
+}
+
+impl<'c, 'a> SeriesCrawler<'c, 'a> {
+    // The number of series in the category
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    // The number of series crawled so far
+    pub fn done(&self) -> usize {
+        self.done
+    }
+}
+
+impl<'c, 'a> Iterator for SeriesCrawler<'c, 'a> {
+    type Item = Result<SeriesSongs>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let series = match self.series.next() {
+            Some(series) => series,
+            None => return None,
+        };
+
+        self.done += 1;
+
+        let songs = {
+            let mut request = self.client
+                .songs()
+                .by_series_in_category_id(&series.title, self.category_id);
+            if self.serial_no.is_some() {
+                request.set_serial_no(self.serial_no.as_ref().map(|s| s.as_str()));
+            }
+
+            request.send_all()
+        };
+
+        Some(songs.map(|songs| {
+            SeriesSongs {
+                series: series,
+                songs: songs.items,
+            }
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.series.size_hint()
+    }
+}
+
+impl<'a> Client<'a> {
+    // Lists every series in the category up front; songs are fetched as the crawler
+    // is iterated. If `serial_no` is `None`, the client's default serial number is used.
+    pub fn crawl_series<'c>(&'c self,
+                            category: Category<SeriesCategory>,
+                            serial_no: Option<&str>)
+                            -> Result<SeriesCrawler<'c, 'a>> {
+        let mut series_request = self.series().by_category_id(category.id.0);
+        if serial_no.is_some() {
+            series_request.set_serial_no(serial_no);
+        }
+
+        let series = series_request.send_all()?.items;
+
+        Ok(SeriesCrawler {
+            client: self,
+            category_id: category.id.0,
+            serial_no: serial_no.map(|s| s.to_string()),
+            total: series.len(),
+            done: 0,
+            series: series.into_iter(),
+        })
+    }
+}
@@ -0,0 +1,176 @@
+// Snapshots are written as JSON lines. The first line is a header:
+//
+//     {"version":1,"serialNo":"AB316238","fetchedAt":1490000000,"categories":["030100"]}
+//
+// Each following line is a record holding exactly one of `song`, `artist` or `series`,
+// along with the ID of the category it was found in and when it was fetched. Timestamps
+// are seconds since the Unix epoch. `serialNo` is null if no serial number was set.
+//
+//     {"category":"030100","fetchedAt":1490000001,"artist":{"id":1234,"name":"..."}}
+//     {"category":"030100","fetchedAt":1490000001,"song":{"id":360715,"title":"...",...}}
+//
+// An artist's record is written once, before the first of their songs. In series
+// categories, each series' record is followed by its songs. `version` is bumped
+// whenever the format changes in a way that would break existing readers.
+
+use error::*;
+use model::{Artist, ArtistId, Series, Song};
+use series::SeriesSongs;
+use serde_json;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u32,
+    #[serde(rename = "serialNo")]
+    pub serial_no: Option<String>,
+    #[serde(rename = "fetchedAt")]
+    pub fetched_at: u64,
+    pub categories: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub category: String,
+    #[serde(rename = "fetchedAt")]
+    pub fetched_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub song: Option<Song>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<Artist>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<Series>,
+}
+
+impl Record {
+    fn new(category_id: &str, fetched_at: u64) -> Self {
+        Record {
+            category: category_id.to_string(),
+            fetched_at: fetched_at,
+            song: None,
+            artist: None,
+            series: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    pub header: Header,
+    pub records: Vec<Record>,
+    artist_ids: HashSet<ArtistId>,
+}
+
+// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl Snapshot {
+    pub fn new(serial_no: Option<&str>) -> Self {
+        Snapshot {
+            header: Header {
+                version: VERSION,
+                serial_no: serial_no.map(|s| s.to_string()),
+                fetched_at: now(),
+                categories: Vec::new(),
+            },
+            records: Vec::new(),
+            artist_ids: HashSet::new(),
+        }
+    }
+
+    pub fn add_songs<S>(&mut self, category_id: &str, songs: S)
+        where S: IntoIterator<Item = Song>
+    {
+        self.add_category(category_id);
+
+        let fetched_at = now();
+        for song in songs {
+            self.add_song(category_id, fetched_at, song);
+        }
+    }
+
+    pub fn add_series(&mut self, category_id: &str, series_songs: SeriesSongs) {
+        self.add_category(category_id);
+
+        let fetched_at = now();
+        let mut record = Record::new(category_id, fetched_at);
+        record.series = Some(series_songs.series);
+        self.records.push(record);
+
+        for song in series_songs.songs {
+            self.add_song(category_id, fetched_at, song);
+        }
+    }
+
+    fn add_category(&mut self, category_id: &str) {
+        if !self.header.categories.iter().any(|c| c == category_id) {
+            self.header.categories.push(category_id.to_string());
+        }
+    }
+
+    fn add_song(&mut self, category_id: &str, fetched_at: u64, song: Song) {
+        if self.artist_ids.insert(song.artist.id) {
+            let mut record = Record::new(category_id, fetched_at);
+            record.artist = Some(song.artist.clone());
+            self.records.push(record);
+        }
+
+        let mut record = Record::new(category_id, fetched_at);
+        record.song = Some(song);
+        self.records.push(record);
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer(&mut writer, &self.header)?;
+        writer.write_all(b"\n").chain_err(|| "failed to write snapshot")?;
+
+        for record in &self.records {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n").chain_err(|| "failed to write snapshot")?;
+        }
+
+        writer.flush().chain_err(|| "failed to write snapshot")
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        let mut lines = reader.lines().filter(|line| match *line {
+            Ok(ref line) => !line.trim().is_empty(),
+            Err(_) => true,
+        });
+
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(&line.chain_err(|| "failed to read snapshot")?)?,
+            None => Err("snapshot is empty")?,
+        };
+
+        if header.version != VERSION {
+            Err(format!("unsupported snapshot version {} (expected {})", header.version, VERSION))?
+        }
+
+        let mut records = Vec::new();
+        let mut artist_ids = HashSet::new();
+
+        for line in lines {
+            let line = line.chain_err(|| "failed to read snapshot")?;
+            let record: Record = serde_json::from_str(&line)?;
+
+            if let Some(ref artist) = record.artist {
+                artist_ids.insert(artist.id);
+            }
+
+            records.push(record);
+        }
+
+        Ok(Snapshot {
+            header: header,
+            records: records,
+            artist_ids: artist_ids,
+        })
+    }
+}
@@ -6,3 +6,122 @@ pub fn deserialize_string_as_u32<D: Deserializer>(deserializer: D) -> Result<u32
         s.parse::<u32>().map_err(|_| de::Error::custom(de::Unexpected::Other("non-numeric string")))
     })
 }
+
+// Accepts "2017/03/15", "2017-03-15" or "20170315" and returns "2017/03/15",
+// which is the format used for dates in `Song`. Dates that don't exist are rejected.
+pub fn normalize_date(input: &str) -> Option<String> {
+    let digits = input.chars().filter(|c| *c != '/' && *c != '-').collect::<String>();
+
+    if digits.len() != 8 || !digits.chars().all(|c| c.is_digit(10)) {
+        return None;
+    }
+
+    let is_valid = match (digits[..4].parse::<u32>(),
+                          digits[4..6].parse::<u32>(),
+                          digits[6..].parse::<u32>()) {
+        (Ok(year), Ok(month @ 1...12), Ok(day)) => day >= 1 && day <= days_in_month(year, month),
+        _ => false,
+    };
+
+    if is_valid {
+        Some(format!("{}/{}/{}", &digits[..4], &digits[4..6], &digits[6..]))
+    } else {
+        None
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Normalizes names for comparison, ignoring case, whitespace, and
+// differences between full-width and half-width characters
+pub fn normalize(input: &str) -> String {
+    input.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c as u32 {
+            // Full-width ASCII variants (e.g., "Ａ") to their half-width equivalents
+            0xFF01...0xFF5E => ::std::char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+// Days since 1970/01/01 for a date in any format accepted by `normalize_date`
+pub fn days_since_epoch(input: &str) -> Option<i64> {
+    let date = match normalize_date(input) {
+        Some(date) => date,
+        None => return None,
+    };
+
+    let (year, month, day) = match (date[..4].parse::<i64>(),
+                                    date[5..7].parse::<i64>(),
+                                    date[8..].parse::<i64>()) {
+        (Ok(year), Ok(month @ 1...12), Ok(day @ 1...31)) => (year, month, day),
+        _ => return None,
+    };
+
+    // Based on http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_date_formats() {
+        assert_eq!(normalize_date("2017/03/15"), Some("2017/03/15".to_string()));
+        assert_eq!(normalize_date("2017-03-15"), Some("2017/03/15".to_string()));
+        assert_eq!(normalize_date("20170315"), Some("2017/03/15".to_string()));
+        assert_eq!(normalize_date("2017/3/15"), None);
+        assert_eq!(normalize_date("someday"), None);
+    }
+
+    #[test]
+    fn normalize_date_rejects_impossible_dates() {
+        assert_eq!(normalize_date("2017/13/45"), None);
+        assert_eq!(normalize_date("2017/00/10"), None);
+        assert_eq!(normalize_date("2017/04/31"), None);
+        assert_eq!(normalize_date("2017/02/29"), None);
+        assert_eq!(normalize_date("1900/02/29"), None);
+        assert_eq!(normalize_date("2000/02/29"), Some("2000/02/29".to_string()));
+        assert_eq!(normalize_date("2016/02/29"), Some("2016/02/29".to_string()));
+    }
+
+    #[test]
+    fn days_since_epoch_in_each_format() {
+        assert_eq!(days_since_epoch("1970/01/01"), Some(0));
+        assert_eq!(days_since_epoch("2017/03/15"), Some(17240));
+        assert_eq!(days_since_epoch("2017-03-15"), Some(17240));
+        assert_eq!(days_since_epoch("20170315"), Some(17240));
+    }
+
+    #[test]
+    fn days_since_epoch_around_leap_days() {
+        assert_eq!(days_since_epoch("2000/02/29"), Some(11016));
+        assert_eq!(days_since_epoch("2000/03/01"), Some(11017));
+        assert_eq!(days_since_epoch("1969/12/31"), Some(-1));
+    }
+
+    #[test]
+    fn days_since_epoch_invalid() {
+        assert_eq!(days_since_epoch("2017/13/01"), None);
+        assert_eq!(days_since_epoch("2017/03/00"), None);
+        assert_eq!(days_since_epoch("2017/3/15"), None);
+        assert_eq!(days_since_epoch("someday"), None);
+    }
+}
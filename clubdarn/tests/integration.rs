@@ -44,3 +44,34 @@ fn songs_by_title() {
     let response_empty = request.set_serial_no(Some("AB316238")).send().unwrap();
     assert!(response_empty.items.is_empty());
 }
+
+#[test]
+fn snapshot_round_trip() {
+    use clubdarn::snapshot::Snapshot;
+
+    let song = Song {
+        id: SongId(366869),
+        title: "Wake up my music".into(),
+        date_added: Some("2013/11/02".into()),
+        end_date: None,
+        lyrics: None,
+        series: None,
+        first_kana: None,
+        has_video: false,
+        artist: Artist {
+            id: ArtistId(96028),
+            name: "りさ、えいみ".into(),
+            first_kana: None,
+        },
+    };
+
+    let mut snapshot = Snapshot::new(Some("AB316238"));
+    snapshot.add_songs("030100", vec![song]);
+
+    let mut written = Vec::new();
+    snapshot.write(&mut written).unwrap();
+
+    // Header, artist, song
+    assert_eq!(String::from_utf8_lossy(&written).lines().count(), 3);
+    assert_eq!(Snapshot::read(&written[..]).unwrap(), snapshot);
+}